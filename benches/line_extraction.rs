@@ -0,0 +1,82 @@
+//! Throughput of extracting lines from a command's output, run with `cargo bench`.
+//!
+//! Streams a large synthetic file of stern-like JSON lines through `cat` and
+//! takes lines out of `CommandStreamer` one at a time and all at once.
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/command_streamer/mod.rs"]
+mod command_streamer;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/string_utils.rs"]
+mod string_utils;
+
+use anyhow::Result;
+use command_streamer::CommandStreamer;
+use std::env::temp_dir;
+use std::fs::{remove_file, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+const COUNT_LINES: usize = 1_000_000;
+
+fn write_input(pathname: &Path) -> Result<usize> {
+    let mut writer = BufWriter::new(File::create(pathname)?);
+    let mut size = 0;
+
+    for iline in 0..COUNT_LINES {
+        let line = format!(
+            "{{\"message\":\"2025-01-01T00:00:00.000Z INFO request {} handled in {}ms\",\"nodeName\":\"node-{}\",\"namespace\":\"default\",\"podName\":\"api-7d9f8c6b5-x{}\",\"containerName\":\"app\"}}\n",
+            iline,
+            iline % 1000,
+            iline % 16,
+            iline % 8
+        );
+
+        size += line.len();
+        writer.write_all(line.as_bytes())?;
+    }
+
+    writer.flush()?;
+
+    return Ok(size);
+}
+
+fn bench(name: &str, pathname: &Path, size: usize, count_lines: i128) -> Result<()> {
+    let mut streamer = CommandStreamer::new("cat", &vec![pathname.display().to_string()], None)?;
+    let mut extracted = 0;
+    let started = Instant::now();
+
+    while !streamer.is_eof() || streamer.has_data_in_buffers() {
+        if let Some(lines) = streamer.get_lines(count_lines, true, false).0? {
+            extracted += lines.len();
+        }
+    }
+
+    let elapsed = started.elapsed().as_secs_f64();
+
+    assert_eq!(extracted, size);
+
+    println!(
+        "{:<16} {:>8.1} MB/s {:>12.0} lines/s",
+        name,
+        size as f64 / elapsed / 1_000_000.0,
+        COUNT_LINES as f64 / elapsed
+    );
+
+    return Ok(());
+}
+
+fn main() -> Result<()> {
+    let pathname = temp_dir().join(format!("doh_line_extraction_{}.jsonl", std::process::id()));
+    let size = write_input(&pathname)?;
+
+    println!("{} lines, {:.1} MB", COUNT_LINES, size as f64 / 1_000_000.0);
+
+    let result = bench("line by line", &pathname, size, 1)
+        .and_then(|_| bench("all lines", &pathname, size, -1));
+
+    remove_file(&pathname)?;
+
+    return result;
+}
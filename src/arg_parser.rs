@@ -11,9 +11,11 @@ use crate::{
 
 pub struct ArgParser {
     pub kv_args: BTreeMap<String, String>,
+    pub kv_args_values: BTreeMap<String, Vec<String>>, // all values of repeated kv_args
     pub args: Vec<String>,
     pub main_arg: String,
     pub ext_kv_args: BTreeMap<String, String>,
+    pub ext_kv_args_values: BTreeMap<String, Vec<String>>,
     pub ext_args: Vec<String>,
     pub ext_main_arg: String,
     pub unknown_args: Vec<String>,
@@ -55,9 +57,11 @@ impl ArgParser {
 
         return ArgParser {
             kv_args: parsed_args.0 .0,
+            kv_args_values: parsed_args.0 .3,
             args: parsed_args.0 .1,
             main_arg: parsed_args.0 .2,
             ext_kv_args: parsed_args.1 .0,
+            ext_kv_args_values: parsed_args.1 .3,
             ext_args: parsed_args.1 .1,
             ext_main_arg: parsed_args.1 .2,
             unknown_args: parsed_args.2,
@@ -110,6 +114,24 @@ impl ArgParser {
         }
     }
 
+    /// Returns every value of a kv arg given more than once, like `--grep a --grep b`,
+    /// `get_kv_arg_string` returns only the last one.
+    pub fn get_kv_arg_values(&self, name: &str, ext: bool) -> Vec<String> {
+        let values = if ext {
+            self.ext_kv_args_values.get(name)
+        } else {
+            self.kv_args_values.get(name)
+        };
+
+        return match values {
+            Some(values) => values.clone(),
+            None => match self.get_kv_arg_string(name, false, ext) {
+                Some(value) => vec![value],
+                None => vec![],
+            },
+        };
+    }
+
     pub fn get_kv_arg_string(&self, name: &str, should_panic: bool, ext: bool) -> Option<String> {
         let value;
 
@@ -229,13 +251,25 @@ impl ArgParser {
         support_main_arg: bool,
         support_main_ext_arg: bool,
     ) -> (
-        (BTreeMap<String, String>, Vec<String>, String),
-        (BTreeMap<String, String>, Vec<String>, String),
+        (
+            BTreeMap<String, String>,
+            Vec<String>,
+            String,
+            BTreeMap<String, Vec<String>>,
+        ),
+        (
+            BTreeMap<String, String>,
+            Vec<String>,
+            String,
+            BTreeMap<String, Vec<String>>,
+        ),
         Vec<String>,
     ) {
         let mut parsed_kv_args: BTreeMap<String, String> = BTreeMap::new();
+        let mut parsed_kv_args_values: BTreeMap<String, Vec<String>> = BTreeMap::new();
         let mut parsed_args = vec![];
         let mut parsed_ext_kv_args: BTreeMap<String, String> = BTreeMap::new();
+        let mut parsed_ext_kv_args_values: BTreeMap<String, Vec<String>> = BTreeMap::new();
         let mut parsed_ext_args = vec![];
         let mut parsed_main_arg = String::new();
         let mut parsed_main_ext_arg = String::new();
@@ -262,6 +296,7 @@ impl ArgParser {
                 supported_args,
                 &mut args,
                 &mut parsed_kv_args,
+                &mut parsed_kv_args_values,
                 &mut parsed_args,
             );
         }
@@ -282,6 +317,7 @@ impl ArgParser {
                 supported_ext_args,
                 &mut args_ext,
                 &mut parsed_ext_kv_args,
+                &mut parsed_ext_kv_args_values,
                 &mut parsed_ext_args,
             );
         }
@@ -300,10 +336,16 @@ impl ArgParser {
             &mut parsed_ext_args,
         );
 
-        Self::merge_args(args_to_merge, &mut parsed_kv_args, &mut parsed_args);
+        Self::merge_args(
+            args_to_merge,
+            &mut parsed_kv_args,
+            &mut parsed_kv_args_values,
+            &mut parsed_args,
+        );
         Self::merge_args(
             ext_args_to_merge,
             &mut parsed_ext_kv_args,
+            &mut parsed_ext_kv_args_values,
             &mut parsed_ext_args,
         );
 
@@ -311,8 +353,18 @@ impl ArgParser {
         unknown_args.extend_from_slice(&args_ext);
 
         return (
-            (parsed_kv_args, parsed_args, parsed_main_arg),
-            (parsed_ext_kv_args, parsed_ext_args, parsed_main_ext_arg),
+            (
+                parsed_kv_args,
+                parsed_args,
+                parsed_main_arg,
+                parsed_kv_args_values,
+            ),
+            (
+                parsed_ext_kv_args,
+                parsed_ext_args,
+                parsed_main_ext_arg,
+                parsed_ext_kv_args_values,
+            ),
             unknown_args,
         );
     }
@@ -322,6 +374,7 @@ impl ArgParser {
         supported_args: &[&str],
         args: &mut Vec<String>,
         parsed_kv_args: &mut BTreeMap<String, String>,
+        parsed_kv_args_values: &mut BTreeMap<String, Vec<String>>,
         parsed_args: &mut Vec<String>,
     ) {
         for iarg in args.clone() {
@@ -336,30 +389,34 @@ impl ArgParser {
             let mut supported_arg_postfix = supported_arg.to_string();
             supported_arg_postfix.push_str("=");
 
-            for (iarg_index, iarg) in args.clone().iter().enumerate() {
-                if iarg == supported_arg {
+            // removing while iterating, so index is advanced only if nothing was removed
+            let mut iarg_index = 0;
+
+            while iarg_index < args.len() {
+                let iarg = args[iarg_index].to_string();
+                let mut value = None;
+
+                if iarg == *supported_arg {
                     if args.len() > iarg_index + 1 {
                         args.remove(iarg_index);
 
-                        let value = args.remove(iarg_index);
+                        value = Some(args.remove(iarg_index));
+                    }
+                } else if let Some(iarg_part_1) = iarg.strip_prefix(&supported_arg_postfix) {
+                    args.remove(iarg_index);
+
+                    value = Some(iarg_part_1.to_string());
+                }
 
+                match value {
+                    Some(value) => {
+                        parsed_kv_args_values
+                            .entry(supported_arg.to_string())
+                            .or_default()
+                            .push(value.to_string());
                         parsed_kv_args.insert(supported_arg.to_string(), value);
                     }
-                } else if iarg.starts_with(&supported_arg_postfix) {
-                    let iarg_parts = iarg
-                        .splitn(2, "=")
-                        .map(|s| s.to_string())
-                        .collect::<Vec<String>>();
-
-                    match iarg_parts.get(1) {
-                        Some(iarg_part_1) => {
-                            parsed_kv_args
-                                .insert(supported_arg.to_string(), iarg_part_1.to_string());
-                        }
-                        None => continue,
-                    }
-
-                    args.remove(iarg_index);
+                    None => iarg_index += 1,
                 }
             }
         }
@@ -387,6 +444,7 @@ impl ArgParser {
     fn merge_args(
         merge_args: &[&[&str; 2]],
         parsed_kv_args: &mut BTreeMap<String, String>,
+        parsed_kv_args_values: &mut BTreeMap<String, Vec<String>>,
         parsed_args: &mut Vec<String>,
     ) {
         for to_merge_args in merge_args {
@@ -407,6 +465,13 @@ impl ArgParser {
                     break;
                 }
             }
+
+            if let Some(mut values) = parsed_kv_args_values.remove(to_merge_args[1]) {
+                parsed_kv_args_values
+                    .entry(to_merge_args[0].to_string())
+                    .or_default()
+                    .append(&mut values);
+            }
         }
     }
 }
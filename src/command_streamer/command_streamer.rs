@@ -1,26 +1,32 @@
 use anyhow::{Error, Result};
-use nonblock::NonBlockingReader;
 use std::{
-    process::{Child, ChildStderr, ChildStdout, Command, Stdio},
+    process::{Child, Command, ExitStatus, Stdio},
     time::Duration,
 };
 
+use super::line_buffer::LineBuffer;
+use super::pipe_reader::PipeReader;
 use crate::string_utils::trim_lines;
 
+const WAIT_FOR_DATA_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// A struct that provides non-blocking streaming capabilities for command execution.
 ///
 /// This implementation allows for capturing and processing the stdout and stderr streams
-/// of a spawned child process in a non-blocking manner. It provides methods to manage
-/// buffers, check for EOF, and extract lines from the output streams.
+/// of a spawned child process in a non-blocking manner, each stream is read by its own
+/// `PipeReader` thread. It provides methods to manage buffers, check for EOF, wait for
+/// data and extract lines from the output streams.
 pub struct CommandStreamer {
     child: Option<Child>,
-    noblock_stdout: Option<NonBlockingReader<ChildStdout>>,
-    noblock_stderr: Option<NonBlockingReader<ChildStderr>>,
-    stdout_buffer: String,
-    stderr_buffer: String,
+    stdout_reader: Option<PipeReader>,
+    stderr_reader: Option<PipeReader>,
+    stdout_buffer: LineBuffer,
+    stderr_buffer: LineBuffer,
     stdout_last_used: bool,
     stdout_at_eof: bool,
     stderr_at_eof: bool,
+    data_generation: u64,
+    exit_status: Option<ExitStatus>,
     program: Option<String>,
     args: Option<Vec<String>>,
     pub user_data: Option<String>,
@@ -35,38 +41,17 @@ impl CommandStreamer {
             return Err(Error::msg("both stdout and stderr are none"));
         }
 
-        let mut noblock_stdout = None;
-        let mut noblock_stderr = None;
-
-        if stdout_option.is_some() {
-            let from_fd_result = NonBlockingReader::from_fd(stdout_option.unwrap());
-
-            if from_fd_result.is_ok() {
-                noblock_stdout = Some(from_fd_result.unwrap());
-            }
-        }
-
-        if stderr_option.is_some() {
-            let from_fd_result = NonBlockingReader::from_fd(stderr_option.unwrap());
-
-            if from_fd_result.is_ok() {
-                noblock_stderr = Some(from_fd_result.unwrap());
-            }
-        }
-
-        if noblock_stdout.is_none() && noblock_stderr.is_none() {
-            return Err(Error::msg("both stdout and stderr are none"));
-        }
-
         return Ok(CommandStreamer {
             child: Some(child),
-            noblock_stdout,
-            noblock_stderr,
-            stdout_buffer: String::new(),
-            stderr_buffer: String::new(),
+            stdout_reader: stdout_option.map(PipeReader::new),
+            stderr_reader: stderr_option.map(PipeReader::new),
+            stdout_buffer: LineBuffer::new(),
+            stderr_buffer: LineBuffer::new(),
             stdout_last_used: false,
             stdout_at_eof: false,
             stderr_at_eof: false,
+            data_generation: 0,
+            exit_status: None,
             program: None,
             args: None,
             user_data: user_data,
@@ -102,6 +87,23 @@ impl CommandStreamer {
         return self;
     }
 
+    /// Sets whether invalid UTF-8 in the output is replaced by U+FFFD (the default)
+    /// or makes `get_lines` fail.
+    pub fn set_lossy_utf8(&mut self, lossy_utf8: bool) -> &mut Self {
+        self.stdout_buffer.set_lossy(lossy_utf8);
+        self.stderr_buffer.set_lossy(lossy_utf8);
+        return self;
+    }
+
+    pub fn is_lossy_utf8(&self) -> bool {
+        return self.stdout_buffer.is_lossy();
+    }
+
+    /// Returns the number of invalid UTF-8 sequences replaced since the last call.
+    pub fn take_replaced_chars(&mut self) -> usize {
+        return self.stdout_buffer.take_replaced_chars() + self.stderr_buffer.take_replaced_chars();
+    }
+
     pub fn get_program(&self) -> &Option<String> {
         return &self.program;
     }
@@ -111,44 +113,75 @@ impl CommandStreamer {
     }
 
     pub fn format_command(&self) -> Result<String> {
-        let mut full_args: Vec<String> = vec![];
-
-        match &self.program {
-            Some(program) => full_args.push(program.to_string()),
+        let program = match &self.program {
+            Some(program) => program,
             None => return Err(Error::msg("program not set")),
-        }
+        };
 
-        match &self.args {
-            Some(args) => full_args.append(&mut args.clone()),
-            None => return Err(Error::msg("args not set")),
-        }
+        return match &self.args {
+            Some(args) => Ok(Self::format_program_args(program, args)),
+            None => Err(Error::msg("args not set")),
+        };
+    }
 
-        return Ok(full_args.join(" "));
+    /// Formats a command like `format_command` does, also for a command not run yet.
+    pub fn format_program_args(program: &str, args: &[String]) -> String {
+        let mut full_args: Vec<String> = vec![program.to_string()];
+
+        full_args.extend_from_slice(args);
+
+        return full_args.join(" ");
     }
 
     pub fn get_child(&mut self) -> Option<&mut Child> {
         return self.child.as_mut();
     }
 
+    /// Returns exit status of the child once it has exited, without waiting for it.
+    pub fn try_exit_status(&mut self) -> Option<ExitStatus> {
+        if self.exit_status.is_none() {
+            if let Some(child) = self.child.as_mut() {
+                if let Ok(Some(exit_status)) = child.try_wait() {
+                    self.exit_status = Some(exit_status);
+                }
+            }
+        }
+
+        return self.exit_status;
+    }
+
+    /// Returns exit status of the child, waits for it to exit if needed.
+    pub fn wait_exit_status(&mut self) -> Option<ExitStatus> {
+        if self.exit_status.is_none() {
+            if let Some(child) = self.child.as_mut() {
+                if let Ok(exit_status) = child.wait() {
+                    self.exit_status = Some(exit_status);
+                }
+            }
+        }
+
+        return self.exit_status;
+    }
+
     pub fn has_data_in_buffers(&self) -> bool {
         return !self.stdout_buffer.is_empty() || !self.stderr_buffer.is_empty();
     }
 
     pub fn get_stdout_buffer(&self) -> String {
-        return self.stdout_buffer.clone();
+        return String::from_utf8_lossy(self.stdout_buffer.as_bytes()).into_owned();
     }
 
     pub fn get_stderr_buffer(&self) -> String {
-        return self.stderr_buffer.clone();
+        return String::from_utf8_lossy(self.stderr_buffer.as_bytes()).into_owned();
     }
 
     pub fn is_eof(&mut self) -> bool {
         let mut count_eof: u8 = 0;
 
         if !self.stdout_at_eof {
-            match &self.noblock_stdout {
-                Some(noblock_stdout) => {
-                    if noblock_stdout.is_eof() {
+            match &self.stdout_reader {
+                Some(stdout_reader) => {
+                    if stdout_reader.is_eof() {
                         count_eof += 1;
 
                         self.stdout_at_eof = true;
@@ -161,9 +194,9 @@ impl CommandStreamer {
         }
 
         if !self.stderr_at_eof {
-            match &self.noblock_stderr {
-                Some(noblock_stderr) => {
-                    if noblock_stderr.is_eof() {
+            match &self.stderr_reader {
+                Some(stderr_reader) => {
+                    if stderr_reader.is_eof() {
                         count_eof += 1;
 
                         self.stderr_at_eof = true;
@@ -175,108 +208,101 @@ impl CommandStreamer {
             count_eof += 1;
         }
 
-        return count_eof == 2;
+        if count_eof == 2 {
+            // collected as soon as possible, the child could be gone later
+            self.try_exit_status();
+
+            return true;
+        }
+
+        return false;
     }
 
     pub fn fill_buffers(&mut self) -> Result<()> {
+        // taken before reading, data read afterwards wakes up `wait_for_data`
+        self.data_generation = PipeReader::get_generation();
+
         if !self.stdout_at_eof {
-            if self.noblock_stdout.is_some() {
-                self.noblock_stdout
-                    .as_mut()
-                    .unwrap()
-                    .read_available_to_string(&mut self.stdout_buffer)?;
+            if let Some(stdout_reader) = self.stdout_reader.as_mut() {
+                self.stdout_buffer.read_available(stdout_reader)?;
             }
         }
 
         if !self.stderr_at_eof {
-            if self.noblock_stderr.is_some() {
-                self.noblock_stderr
-                    .as_mut()
-                    .unwrap()
-                    .read_available_to_string(&mut self.stderr_buffer)?;
+            if let Some(stderr_reader) = self.stderr_reader.as_mut() {
+                self.stderr_buffer.read_available(stderr_reader)?;
             }
         }
 
         return Ok(());
     }
 
-    fn buffer_vec_line_pos(&mut self, buffer_vec: &mut Vec<char>) -> Option<usize> {
-        let mut end: usize = 0;
-
-        loop {
-            if end >= buffer_vec.len() {
-                return None;
-            }
-
-            let c = buffer_vec[end];
-
-            end += 1;
-
-            if c == '\n' || c == '\r' {
-                return Some(end);
-            }
-        }
+    /// Returns true if `get_lines` has something to return, complete lines or
+    /// the rest of a stream which ended without a new line.
+    pub fn has_lines_in_buffers(&self) -> bool {
+        return self.stdout_buffer.has_line()
+            || self.stderr_buffer.has_line()
+            || (self.is_reader_at_eof(true) && !self.stdout_buffer.is_empty())
+            || (self.is_reader_at_eof(false) && !self.stderr_buffer.is_empty());
     }
 
-    fn buffer_vec_extract_lines(
-        &mut self,
-        buffer_vec: &mut Vec<char>,
-        count_lines: i128,
-    ) -> (Option<String>, bool) {
-        let mut lines = String::new();
-        let mut buffer_affected = false;
-        let mut extracted: i128 = 0;
-
-        loop {
-            let buffer_vec_line_pos_option = self.buffer_vec_line_pos(buffer_vec);
+    /// Returns true once both streams were read to the end and their buffers are empty.
+    pub fn is_drained(&self) -> bool {
+        return self.is_reader_at_eof(true)
+            && self.is_reader_at_eof(false)
+            && !self.has_data_in_buffers();
+    }
 
-            match buffer_vec_line_pos_option {
-                None => break,
-                _ => {}
-            }
+    fn is_reader_at_eof(&self, stdout_reader: bool) -> bool {
+        let reader = if stdout_reader {
+            &self.stdout_reader
+        } else {
+            &self.stderr_reader
+        };
 
-            let lines_vec: Vec<char> = buffer_vec
-                .drain(0..buffer_vec_line_pos_option.unwrap())
-                .collect();
+        return reader.as_ref().is_none_or(|reader| reader.is_eof());
+    }
 
-            lines.push_str(String::from_iter(lines_vec).as_str());
+    pub fn get_data_generation(&self) -> u64 {
+        return self.data_generation;
+    }
 
-            buffer_affected = true;
-            extracted += 1;
+    /// Blocks until new data or EOF can be read by `fill_buffers` or the timeout passes,
+    /// returns at once if there are complete lines in the buffers already or both
+    /// streams were read to the end.
+    pub fn wait_for_data(&self, timeout: Duration) -> bool {
+        if self.has_lines_in_buffers() {
+            return true;
+        }
 
-            if count_lines != -1 {
-                if extracted >= count_lines {
-                    break;
-                }
-            }
+        if self.is_reader_at_eof(true) && self.is_reader_at_eof(false) {
+            return true;
         }
 
-        return (Some(lines), buffer_affected);
+        return PipeReader::wait_for_data(self.data_generation, timeout);
     }
 
-    fn get_buffer_lines(&mut self, stdout_buffer: bool, count_lines: i128) -> Option<String> {
-        let mut buffer_vec: Vec<char>;
-
-        if stdout_buffer {
-            buffer_vec = self.stdout_buffer.chars().collect();
+    fn get_buffer_lines(
+        &mut self,
+        stdout_buffer: bool,
+        count_lines: i128,
+    ) -> Result<Option<String>> {
+        let at_eof = self.is_reader_at_eof(stdout_buffer);
+        let buffer = if stdout_buffer {
+            &mut self.stdout_buffer
         } else {
-            buffer_vec = self.stderr_buffer.chars().collect();
-        }
-
-        let (lines_option, buffer_affected) =
-            self.buffer_vec_extract_lines(&mut buffer_vec, count_lines);
+            &mut self.stderr_buffer
+        };
 
-        if buffer_affected {
-            let buffer_new = String::from_iter(buffer_vec);
+        let mut lines = buffer.take_lines(count_lines)?;
 
-            if stdout_buffer {
-                self.stdout_buffer = buffer_new;
-            } else {
-                self.stderr_buffer = buffer_new;
-            }
+        // the last line of a stream which did not end with a new line
+        if lines.is_none() && at_eof {
+            lines = buffer.take_rest()?;
         }
 
-        return lines_option;
+        // no complete line yet
+        return Ok(Some(lines.unwrap_or_default()));
     }
 
     pub fn get_lines(
@@ -311,7 +337,10 @@ impl CommandStreamer {
             return (Ok(None), self, self.stdout_last_used);
         }
 
-        let mut lines_option = self.get_buffer_lines(self.stdout_last_used, count_lines);
+        let mut lines_option = match self.get_buffer_lines(self.stdout_last_used, count_lines) {
+            Ok(lines_option) => lines_option,
+            Err(err) => return (Err(err), self, self.stdout_last_used),
+        };
 
         if trim {
             lines_option = Self::trim_lines(lines_option);
@@ -335,25 +364,70 @@ impl CommandStreamer {
         };
     }
 
-    pub fn get_all_lines(&mut self, fill_buffers: bool, trim: bool) -> Result<Option<String>> {
-        let mut lines = String::new();
+    /// Reads both streams to the end, returns stdout and stderr lines separately,
+    /// so errors and warnings of a command do not break parsing of its output.
+    pub fn get_all_lines(
+        &mut self,
+        fill_buffers: bool,
+        trim: bool,
+    ) -> Result<(Option<String>, Option<String>)> {
+        let mut stdout_lines = String::new();
+        let mut stderr_lines = String::new();
 
         if fill_buffers {
             self.fill_buffers()?;
         }
 
         while !self.is_eof() || self.has_data_in_buffers() {
-            match self.get_lines(-1, true, trim).0 {
-                Ok(option) => match option {
-                    Some(ilines) => lines.push_str(&ilines),
-                    None => {}
-                },
-                Err(e) => return Err(e),
+            let (lines_result, _, is_stdout) = self.get_lines(-1, true, trim);
+
+            if let Some(ilines) = lines_result? {
+                if is_stdout {
+                    stdout_lines.push_str(&ilines);
+                } else {
+                    stderr_lines.push_str(&ilines);
+                }
             }
 
-            std::thread::sleep(Duration::from_secs(0));
+            self.wait_for_data(WAIT_FOR_DATA_TIMEOUT);
         }
 
-        return Ok(Some(lines));
+        return Ok((Some(stdout_lines), Some(stderr_lines)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_all_lines_without_trailing_new_line() {
+        let mut streamer =
+            CommandStreamer::new("printf", &vec!["first\\nlast".to_string()], None).unwrap();
+
+        assert_eq!(
+            streamer.get_all_lines(true, false).unwrap(),
+            (Some("first\nlast".to_string()), Some(String::new()))
+        );
+        assert!(streamer.is_eof());
+        assert!(!streamer.has_data_in_buffers());
+    }
+
+    #[test]
+    fn test_get_all_lines_separates_stderr() {
+        let mut streamer = CommandStreamer::new(
+            "sh",
+            &vec![
+                "-c".to_string(),
+                "echo '{}'; echo warning >&2; echo '[]'".to_string(),
+            ],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            streamer.get_all_lines(true, false).unwrap(),
+            (Some("{}\n[]\n".to_string()), Some("warning\n".to_string()))
+        );
     }
 }
@@ -0,0 +1,255 @@
+use memchr::memchr2;
+use std::char::REPLACEMENT_CHARACTER;
+use std::io;
+
+use super::pipe_reader::PipeReader;
+
+// compact the buffer once at least such number of bytes were consumed
+const COMPACT_THRESHOLD: usize = 64 * 1024;
+
+/// A byte buffer of a command's output which hands out complete lines.
+///
+/// Extracted lines are only skipped by moving `start`, the remainder is moved
+/// to the front once the consumed part gets large, not on every extraction.
+///
+/// Only complete lines are decoded, so a multibyte character split between reads
+/// stays intact; invalid UTF-8 is replaced by U+FFFD unless `lossy` is off.
+pub struct LineBuffer {
+    data: Vec<u8>,
+    start: usize,
+    lossy: bool,
+    replaced_chars: usize,
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        return LineBuffer {
+            data: vec![],
+            start: 0,
+            lossy: true,
+            replaced_chars: 0,
+        };
+    }
+
+    /// Sets whether invalid UTF-8 is replaced (the default) or makes `take_lines` fail.
+    pub fn set_lossy(&mut self, lossy: bool) -> &mut Self {
+        self.lossy = lossy;
+        return self;
+    }
+
+    pub fn is_lossy(&self) -> bool {
+        return self.lossy;
+    }
+
+    /// Returns the number of characters replaced since the last call.
+    pub fn take_replaced_chars(&mut self) -> usize {
+        return std::mem::take(&mut self.replaced_chars);
+    }
+
+    /// Appends data available in `reader` directly to the buffer.
+    pub fn read_available(&mut self, reader: &mut PipeReader) -> io::Result<usize> {
+        return reader.read_available(&mut self.data);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.start >= self.data.len();
+    }
+
+    pub fn has_line(&self) -> bool {
+        return memchr2(b'\n', b'\r', self.as_bytes()).is_some();
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.data[self.start..];
+    }
+
+    /// Returns up to `count_lines` (-1 for all) complete lines, each ending with
+    /// '\n' or '\r', or `None` if there is no complete line yet.
+    ///
+    /// Without `lossy` lines with invalid UTF-8 are an error, they are dropped anyway.
+    pub fn take_lines(&mut self, count_lines: i128) -> io::Result<Option<String>> {
+        let mut end = self.start;
+        let mut extracted: i128 = 0;
+
+        while count_lines == -1 || extracted < count_lines {
+            match memchr2(b'\n', b'\r', &self.data[end..]) {
+                Some(position) => {
+                    end += position + 1;
+                    extracted += 1;
+                }
+                None => break,
+            }
+        }
+
+        if extracted == 0 {
+            return Ok(None);
+        }
+
+        return self.take_until(end);
+    }
+
+    /// Returns the remaining bytes as the last line, once no more data can come,
+    /// like output which does not end with a new line.
+    pub fn take_rest(&mut self) -> io::Result<Option<String>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+
+        return self.take_until(self.data.len());
+    }
+
+    fn take_until(&mut self, end: usize) -> io::Result<Option<String>> {
+        let bytes = &self.data[self.start..end];
+        let result = match std::str::from_utf8(bytes) {
+            Ok(lines) => Ok(Some(lines.to_string())),
+            Err(err) if !self.lossy => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid UTF-8 in output, {}", err),
+            )),
+            Err(_) => {
+                let mut lines = String::with_capacity(bytes.len());
+
+                // like String::from_utf8_lossy, counting the replacements
+                for ichunk in bytes.utf8_chunks() {
+                    lines.push_str(ichunk.valid());
+
+                    if !ichunk.invalid().is_empty() {
+                        lines.push(REPLACEMENT_CHARACTER);
+                        self.replaced_chars += 1;
+                    }
+                }
+
+                Ok(Some(lines))
+            }
+        };
+
+        self.start = end;
+        self.compact();
+
+        return result;
+    }
+
+    fn compact(&mut self) {
+        if self.start == self.data.len() {
+            self.data.clear();
+            self.start = 0;
+        } else if self.start >= COMPACT_THRESHOLD && self.start * 2 >= self.data.len() {
+            self.data.drain(..self.start);
+            self.start = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    fn new_buffer(bytes: &[u8]) -> LineBuffer {
+        let mut buffer = LineBuffer::new();
+
+        buffer.data.extend_from_slice(bytes);
+
+        return buffer;
+    }
+
+    #[test]
+    fn test_take_lines() {
+        let mut buffer = new_buffer(b"a\nb\r\nc\rpartial");
+
+        assert_eq!(buffer.take_lines(1).unwrap(), Some("a\n".to_string()));
+        assert_eq!(buffer.take_lines(2).unwrap(), Some("b\r\n".to_string()));
+        assert_eq!(buffer.take_lines(-1).unwrap(), Some("c\r".to_string()));
+        assert_eq!(buffer.take_lines(-1).unwrap(), None);
+        assert!(!buffer.has_line());
+        assert!(!buffer.is_empty());
+        assert_eq!(buffer.as_bytes(), b"partial");
+    }
+
+    #[test]
+    fn test_take_rest() {
+        let mut buffer = new_buffer(b"line\nlast line");
+
+        assert_eq!(buffer.take_lines(-1).unwrap(), Some("line\n".to_string()));
+        assert_eq!(buffer.take_rest().unwrap(), Some("last line".to_string()));
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.take_rest().unwrap(), None);
+    }
+
+    #[test]
+    fn test_split_multibyte_char() {
+        let bytes = "żółw\n".as_bytes();
+        let mut buffer = new_buffer(&bytes[..1]);
+
+        assert_eq!(buffer.take_lines(-1).unwrap(), None);
+
+        buffer.data.extend_from_slice(&bytes[1..]);
+
+        assert_eq!(buffer.take_lines(-1).unwrap(), Some("żółw\n".to_string()));
+        assert_eq!(buffer.take_replaced_chars(), 0);
+    }
+
+    #[test]
+    fn test_lossy() {
+        let mut buffer = new_buffer(b"bad \xff\xfe byte\nok\n");
+
+        assert_eq!(
+            buffer.take_lines(1).unwrap(),
+            Some("bad \u{FFFD}\u{FFFD} byte\n".to_string())
+        );
+        assert_eq!(buffer.take_replaced_chars(), 2);
+        assert_eq!(buffer.take_replaced_chars(), 0);
+        assert_eq!(buffer.take_lines(1).unwrap(), Some("ok\n".to_string()));
+    }
+
+    #[test]
+    fn test_not_lossy() {
+        let mut buffer = new_buffer(b"bad \xff\nok\n");
+
+        buffer.set_lossy(false);
+
+        assert!(buffer.take_lines(1).is_err());
+        assert_eq!(buffer.take_lines(1).unwrap(), Some("ok\n".to_string()));
+        assert_eq!(buffer.take_replaced_chars(), 0);
+    }
+
+    #[test]
+    fn test_compact() {
+        let line = "x".repeat(99) + "\n";
+        let mut buffer = new_buffer(line.repeat(1000).as_bytes());
+
+        // consumed less than the threshold, nothing moved
+        buffer.take_lines(600).unwrap();
+        assert_eq!(buffer.start, 60_000);
+
+        // consumed at least the threshold and half of the data
+        buffer.take_lines(100).unwrap();
+        assert_eq!(buffer.start, 0);
+        assert_eq!(buffer.data.len(), 30_000);
+        assert_eq!(buffer.take_lines(-1).unwrap(), Some(line.repeat(300)));
+
+        // consumed everything
+        assert_eq!(buffer.data.len(), 0);
+    }
+
+    #[test]
+    fn test_read_available() {
+        let mut reader = PipeReader::new(Cursor::new(b"first\nsecond".to_vec()));
+        let mut buffer = LineBuffer::new();
+
+        while !reader.is_eof() {
+            buffer.read_available(&mut reader).unwrap();
+            PipeReader::wait_for_data(PipeReader::get_generation(), Duration::from_millis(10));
+        }
+
+        assert_eq!(buffer.take_lines(-1).unwrap(), Some("first\n".to_string()));
+        assert_eq!(buffer.take_rest().unwrap(), Some("second".to_string()));
+    }
+}
@@ -1,5 +1,9 @@
 pub mod command_streamer;
+pub mod line_buffer;
 pub mod multi_command_streamer;
+pub mod pipe_reader;
 
 pub use command_streamer::CommandStreamer;
+pub use line_buffer::LineBuffer;
 pub use multi_command_streamer::MultiCommandStreamer;
+pub use pipe_reader::PipeReader;
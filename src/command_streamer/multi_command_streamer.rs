@@ -1,28 +1,83 @@
+use super::pipe_reader::PipeReader;
 use super::CommandStreamer;
 use anyhow::Result;
+use std::collections::VecDeque;
+use std::time::Duration;
 
+struct QueuedCommand {
+    program: String,
+    args: Vec<String>,
+    user_data: Option<String>,
+}
+
+/// Runs many commands at once, optionally only a limited number of groups
+/// (commands with the same `user_data`, e.g. one context) at a time; commands
+/// of other groups wait in a queue until `start_queued` finds a free slot.
 pub struct MultiCommandStreamer {
     streamers: Vec<CommandStreamer>,
+    queue: VecDeque<QueuedCommand>,
+    max_running_groups: Option<usize>,
+    restarts_exited: bool,
+    lossy_utf8: bool,
 }
 
 impl MultiCommandStreamer {
     pub fn new_empty() -> Self {
-        return MultiCommandStreamer { streamers: vec![] };
+        return MultiCommandStreamer {
+            streamers: vec![],
+            queue: VecDeque::new(),
+            max_running_groups: None,
+            restarts_exited: false,
+            lossy_utf8: true,
+        };
     }
 
     pub fn new(program: &str, args: &Vec<String>, user_data: Option<String>) -> Result<Self> {
         return Ok(MultiCommandStreamer {
             streamers: vec![CommandStreamer::new(program, args, user_data)?],
+            queue: VecDeque::new(),
+            max_running_groups: None,
+            restarts_exited: false,
+            lossy_utf8: true,
         });
     }
 
     pub fn new_from_streamer(streamer: CommandStreamer) -> Result<Option<Self>> {
         return Ok(Some(MultiCommandStreamer {
             streamers: vec![streamer],
+            queue: VecDeque::new(),
+            max_running_groups: None,
+            restarts_exited: false,
+            lossy_utf8: true,
         }));
     }
 
-    pub fn add_streamer(&mut self, streamer: CommandStreamer) {
+    /// Queues commands passed to `add` from now on instead of running them.
+    pub fn set_max_running_groups(&mut self, max_running_groups: Option<usize>) -> &mut Self {
+        self.max_running_groups = max_running_groups;
+        return self;
+    }
+
+    /// Tells that exited streamers are run again by the caller (e.g. `StreamSupervisor`),
+    /// so their groups keep the slots and no queued group is started instead.
+    pub fn set_restarts_exited(&mut self, restarts_exited: bool) -> &mut Self {
+        self.restarts_exited = restarts_exited;
+        return self;
+    }
+
+    /// Sets `CommandStreamer::set_lossy_utf8` of all streamers, also of ones added later.
+    pub fn set_lossy_utf8(&mut self, lossy_utf8: bool) -> &mut Self {
+        self.lossy_utf8 = lossy_utf8;
+
+        for streamer in self.streamers.iter_mut() {
+            streamer.set_lossy_utf8(lossy_utf8);
+        }
+
+        return self;
+    }
+
+    pub fn add_streamer(&mut self, mut streamer: CommandStreamer) {
+        streamer.set_lossy_utf8(self.lossy_utf8);
         self.streamers.push(streamer);
     }
 
@@ -32,12 +87,70 @@ impl MultiCommandStreamer {
         args: &Vec<String>,
         user_data: Option<String>,
     ) -> Result<()> {
-        self.streamers
-            .push(CommandStreamer::new(program, args, user_data)?);
+        if self.max_running_groups.is_some() {
+            self.queue.push_back(QueuedCommand {
+                program: program.to_string(),
+                args: args.clone(),
+                user_data,
+            });
+
+            return Ok(());
+        }
+
+        self.add_streamer(CommandStreamer::new(program, args, user_data)?);
 
         return Ok(());
     }
 
+    /// Runs queued groups while fewer than the maximum groups are running,
+    /// returns the started streamers.
+    pub fn start_queued(&mut self) -> Result<&[CommandStreamer]> {
+        let count_streamers = self.streamers.len();
+
+        if let Some(max_running_groups) = self.max_running_groups {
+            let mut running_groups = self.get_running_groups();
+
+            while running_groups.len() < max_running_groups {
+                let user_data = match self.queue.front() {
+                    Some(queued) => queued.user_data.clone(),
+                    None => break,
+                };
+
+                while self
+                    .queue
+                    .front()
+                    .is_some_and(|queued| queued.user_data == user_data)
+                {
+                    let queued = self.queue.pop_front().unwrap();
+
+                    self.add_streamer(CommandStreamer::new(
+                        &queued.program,
+                        &queued.args,
+                        queued.user_data,
+                    )?);
+                }
+
+                running_groups.push(user_data);
+            }
+        }
+
+        return Ok(&self.streamers[count_streamers..]);
+    }
+
+    fn get_running_groups(&mut self) -> Vec<Option<String>> {
+        let mut running_groups = vec![];
+
+        for streamer in self.streamers.iter_mut() {
+            if (self.restarts_exited || !streamer.is_eof() || streamer.has_data_in_buffers())
+                && !running_groups.contains(&streamer.user_data)
+            {
+                running_groups.push(streamer.user_data.clone());
+            }
+        }
+
+        return running_groups;
+    }
+
     pub fn get_streamers(&mut self) -> &mut Vec<CommandStreamer> {
         return &mut self.streamers;
     }
@@ -51,7 +164,7 @@ impl MultiCommandStreamer {
             }
         }
 
-        return count_eof == self.streamers.len();
+        return count_eof == self.streamers.len() && self.queue.is_empty();
     }
 
     pub fn has_data_in_buffers(&self) -> bool {
@@ -64,6 +177,39 @@ impl MultiCommandStreamer {
         return false;
     }
 
+    /// Blocks until any streamer has new data or EOF to read, or the timeout passes;
+    /// returns at once if some streamer has lines to return already. Drained streamers
+    /// are skipped, with all of them drained it just sleeps, e.g. until a restart is due.
+    pub fn wait_for_data(&self, timeout: Duration) -> bool {
+        if self
+            .streamers
+            .iter()
+            .any(|streamer| streamer.has_lines_in_buffers())
+        {
+            return true;
+        }
+
+        // the oldest one, streamers are filled one by one; drained ones get no more data
+        let data_generation = self
+            .streamers
+            .iter()
+            .filter(|streamer| !streamer.is_drained())
+            .map(|streamer| streamer.get_data_generation())
+            .min()
+            .unwrap_or_else(PipeReader::get_generation);
+
+        return PipeReader::wait_for_data(data_generation, timeout);
+    }
+
+    /// Returns the number of invalid UTF-8 sequences replaced since the last call.
+    pub fn take_replaced_chars(&mut self) -> usize {
+        return self
+            .streamers
+            .iter_mut()
+            .map(|streamer| streamer.take_replaced_chars())
+            .sum();
+    }
+
     pub fn fill_buffers(&mut self) -> Vec<Result<()>> {
         let mut results = vec![];
 
@@ -89,7 +235,11 @@ impl MultiCommandStreamer {
         return results;
     }
 
-    pub fn get_all_lines(&mut self, fill_buffers: bool, trim: bool) -> Vec<Result<Option<String>>> {
+    pub fn get_all_lines(
+        &mut self,
+        fill_buffers: bool,
+        trim: bool,
+    ) -> Vec<Result<(Option<String>, Option<String>)>> {
         let mut results = vec![];
 
         for streamer in self.streamers.iter_mut() {
@@ -99,3 +249,38 @@ impl MultiCommandStreamer {
         return results;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streams_without_trailing_new_line_are_drained() {
+        let mut multi_streamer = MultiCommandStreamer::new_empty();
+        let mut lines = String::new();
+
+        multi_streamer
+            .add("printf", &vec!["first\\nlast".to_string()], None)
+            .unwrap();
+        multi_streamer
+            .add("printf", &vec!["other".to_string()], None)
+            .unwrap();
+
+        while !multi_streamer.is_eof() || multi_streamer.has_data_in_buffers() {
+            for (ilines, _, _) in multi_streamer.get_lines(-1, true, false) {
+                lines.push_str(&ilines.unwrap().unwrap_or_default());
+            }
+
+            if !multi_streamer.is_eof() {
+                multi_streamer.wait_for_data(Duration::from_secs(1));
+            }
+        }
+
+        assert_eq!(lines.len(), "first\nlastother".len());
+        assert!(lines.contains("last") && lines.contains("other"));
+        assert!(multi_streamer
+            .get_streamers()
+            .iter()
+            .all(|streamer| streamer.is_drained()));
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::{self, ErrorKind, Read};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+// counts chunks read by all readers, waiters sleep until it changes
+static DATA_GENERATION: Mutex<u64> = Mutex::new(0);
+static DATA_CONDVAR: Condvar = Condvar::new();
+
+/// Reads a pipe (or any blocking reader) in its own thread and hands out the data
+/// read so far without blocking, like `nonblock::NonBlockingReader` but without
+/// polling: `wait_for_data` sleeps until any reader got data or reached EOF.
+pub struct PipeReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    eof: bool,
+}
+
+impl PipeReader {
+    pub fn new<R: Read + Send + 'static>(mut reader: R) -> Self {
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            let mut bytes = vec![0u8; READ_BUFFER_SIZE];
+
+            loop {
+                let result = match reader.read(&mut bytes) {
+                    Ok(0) => break,
+                    Ok(size) => Ok(bytes[..size].to_vec()),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => Err(err),
+                };
+                let is_err = result.is_err();
+
+                if sender.send(result).is_err() || is_err {
+                    break;
+                }
+
+                Self::notify();
+            }
+
+            // the receiver sees the disconnected channel as EOF
+            drop(sender);
+            Self::notify();
+        });
+
+        return PipeReader {
+            receiver,
+            eof: false,
+        };
+    }
+
+    pub fn is_eof(&self) -> bool {
+        return self.eof;
+    }
+
+    /// Appends all data read so far to `buf`, returns its size.
+    pub fn read_available(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let mut size = 0;
+
+        while !self.eof {
+            match self.receiver.try_recv() {
+                Ok(Ok(bytes)) => {
+                    size += bytes.len();
+                    buf.extend_from_slice(&bytes);
+                }
+                Ok(Err(err)) => return Err(err),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.eof = true,
+            }
+        }
+
+        return Ok(size);
+    }
+
+    /// Returns the current data generation, to be taken before reading the available data
+    /// and passed to `wait_for_data` afterwards.
+    pub fn get_generation() -> u64 {
+        return *DATA_GENERATION.lock().unwrap();
+    }
+
+    /// Blocks until any reader got data or reached EOF since `generation`,
+    /// or the timeout passes; returns true in the former case.
+    pub fn wait_for_data(generation: u64, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut current = DATA_GENERATION.lock().unwrap();
+
+        while *current == generation {
+            let now = Instant::now();
+
+            if now >= deadline {
+                return false;
+            }
+
+            current = DATA_CONDVAR
+                .wait_timeout(current, deadline - now)
+                .unwrap()
+                .0;
+        }
+
+        return true;
+    }
+
+    fn notify() {
+        *DATA_GENERATION.lock().unwrap() += 1;
+        DATA_CONDVAR.notify_all();
+    }
+}
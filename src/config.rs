@@ -0,0 +1,252 @@
+use anyhow::{Error, Result};
+use std::collections::BTreeMap;
+use std::env::var_os;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+use crate::consts::APP_NAME;
+use crate::string_utils::tokenize_by;
+
+const LOCAL_CONFIG_FILENAME: &str = ".doh.toml";
+
+/// Settings of `~/.config/doh/config.toml` and the project-local `.doh.toml`, like:
+///
+/// ```toml
+/// [contexts]
+/// prod = ["prod-eu", "prod-us"]
+///
+/// [defaults]
+/// since = "30m"
+///
+/// [profiles.prod-api]
+/// context = "prod"
+/// include-container = "app"
+/// pretty-print-objects = true
+/// ```
+///
+/// Keys of `defaults` and profiles are long option names without the leading dashes,
+/// values of the local file override the global ones. An array is a list of values,
+/// like `grep = ["timeout", "refused"]` for a repeatable option.
+pub struct Config {
+    pub pathnames: Vec<PathBuf>,
+    pub context_groups: BTreeMap<String, Vec<String>>,
+    pub defaults: BTreeMap<String, Vec<String>>,
+    pub profiles: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let mut config = Config {
+            pathnames: vec![],
+            context_groups: BTreeMap::new(),
+            defaults: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        };
+
+        for ipathname in Self::get_pathnames() {
+            if !ipathname.is_file() {
+                continue;
+            }
+
+            let table: Table = match read_to_string(&ipathname)?.parse() {
+                Ok(table) => table,
+                Err(err) => {
+                    return Err(Error::msg(format!(
+                        "cannot parse config \"{}\" ({})",
+                        ipathname.display(),
+                        err
+                    )))
+                }
+            };
+
+            if let Err(err) = config.merge(&table) {
+                return Err(Error::msg(format!(
+                    "invalid config \"{}\" ({})",
+                    ipathname.display(),
+                    err
+                )));
+            }
+
+            config.pathnames.push(ipathname);
+        }
+
+        return Ok(config);
+    }
+
+    /// Returns option values of `defaults` overridden by the profile's ones,
+    /// keyed by the long option name like "--since".
+    pub fn get_kv_args(&self, profile: &Option<String>) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut kv_args: BTreeMap<String, Vec<String>> = self
+            .defaults
+            .iter()
+            .map(|(key, values)| (format!("--{}", key), values.clone()))
+            .collect();
+
+        if let Some(profile) = profile {
+            match self.profiles.get(profile) {
+                Some(profile_values) => kv_args.extend(
+                    profile_values
+                        .iter()
+                        .map(|(key, values)| (format!("--{}", key), values.clone())),
+                ),
+                None => {
+                    return Err(Error::msg(format!(
+                        "profile \"{}\" not found, available profiles: {}",
+                        profile,
+                        self.profiles
+                            .keys()
+                            .map(|name| name.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    )))
+                }
+            }
+        }
+
+        return Ok(kv_args);
+    }
+
+    /// Replaces context group names by their contexts, groups can contain other groups.
+    pub fn expand_context_groups(&self, contexts: &[String]) -> Vec<String> {
+        let mut expanded = vec![];
+
+        self.expand_context_groups_internal(contexts, &mut vec![], &mut expanded);
+
+        return expanded;
+    }
+
+    fn expand_context_groups_internal(
+        &self,
+        contexts: &[String],
+        visited: &mut Vec<String>,
+        expanded: &mut Vec<String>,
+    ) {
+        for icontext in contexts {
+            match self.context_groups.get(icontext) {
+                Some(group) if !visited.contains(icontext) => {
+                    visited.push(icontext.to_string());
+                    self.expand_context_groups_internal(group, visited, expanded);
+                    visited.pop();
+                }
+                _ => {
+                    if !expanded.contains(icontext) {
+                        expanded.push(icontext.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_pathnames() -> Vec<PathBuf> {
+        let mut pathnames = vec![];
+
+        let config_dir = match var_os("XDG_CONFIG_HOME") {
+            Some(config_home) if !config_home.is_empty() => Some(PathBuf::from(config_home)),
+            _ => var_os("HOME")
+                .or_else(|| var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".config")),
+        };
+
+        if let Some(config_dir) = config_dir {
+            pathnames.push(config_dir.join(APP_NAME).join("config.toml"));
+        }
+
+        pathnames.push(PathBuf::from(LOCAL_CONFIG_FILENAME));
+
+        return pathnames;
+    }
+
+    fn merge(&mut self, table: &Table) -> Result<()> {
+        for (key, value) in table {
+            match key.as_str() {
+                "contexts" => {
+                    for (name, contexts) in Self::get_table(key, value)? {
+                        self.context_groups.insert(
+                            name.to_string(),
+                            tokenize_by(
+                                &Self::value_to_strings(&format!("{}.{}", key, name), contexts)?
+                                    .join(","),
+                                ",",
+                                -1,
+                                true,
+                                true,
+                            ),
+                        );
+                    }
+                }
+                "defaults" => {
+                    Self::merge_options(key, Self::get_table(key, value)?, &mut self.defaults)?
+                }
+                "profiles" => {
+                    for (name, profile) in Self::get_table(key, value)? {
+                        let path = format!("{}.{}", key, name);
+
+                        Self::merge_options(
+                            &path,
+                            Self::get_table(&path, profile)?,
+                            self.profiles.entry(name.to_string()).or_default(),
+                        )?;
+                    }
+                }
+                _ => return Err(Error::msg(format!("unknown section \"{}\"", key))),
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn merge_options(
+        path: &str,
+        table: &Table,
+        options: &mut BTreeMap<String, Vec<String>>,
+    ) -> Result<()> {
+        for (key, value) in table {
+            if key == "profile" {
+                return Err(Error::msg(format!(
+                    "\"{}.{}\" cannot be set in a config",
+                    path, key
+                )));
+            }
+
+            options.insert(
+                key.to_string(),
+                Self::value_to_strings(&format!("{}.{}", path, key), value)?,
+            );
+        }
+
+        return Ok(());
+    }
+
+    fn get_table<'a>(path: &str, value: &'a Value) -> Result<&'a Table> {
+        return match value.as_table() {
+            Some(table) => Ok(table),
+            None => Err(Error::msg(format!("\"{}\" should be a table", path))),
+        };
+    }
+
+    fn value_to_strings(path: &str, value: &Value) -> Result<Vec<String>> {
+        return match value {
+            Value::String(s) => Ok(vec![s.to_string()]),
+            Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => Ok(vec![value.to_string()]),
+            Value::Array(values) => {
+                let mut strings = vec![];
+
+                for ivalue in values {
+                    match ivalue {
+                        Value::Array(_) => {
+                            return Err(Error::msg(format!("\"{}\" should be a flat array", path)))
+                        }
+                        _ => strings.extend(Self::value_to_strings(path, ivalue)?),
+                    }
+                }
+
+                Ok(strings)
+            }
+            _ => Err(Error::msg(format!(
+                "\"{}\" should be a string, number, boolean or array",
+                path
+            ))),
+        };
+    }
+}
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::stern_json::SternJSON;
+
+/// All entries sharing the same value of the grouped field, e.g. one request.
+pub struct EntryGroup {
+    pub value: String,
+    pub contexts: Vec<String>,
+    pub entries: Vec<(String, SternJSON)>,
+    last_arrival: Instant,
+}
+
+impl EntryGroup {
+    /// Returns entries as one timeline, entries without a timestamp
+    /// stay right after the entry which preceded them.
+    pub fn into_timeline(self) -> Vec<(String, SternJSON)> {
+        let mut last_datetime: Option<DateTime<Utc>> = None;
+        let mut timeline = vec![];
+
+        for (index, (context, json)) in self.entries.into_iter().enumerate() {
+            if json.datetime.is_some() {
+                last_datetime = json.datetime;
+            }
+
+            timeline.push(((last_datetime, index), context, json));
+        }
+
+        timeline.sort_by_key(|(key, _, _)| *key);
+
+        return timeline
+            .into_iter()
+            .map(|(_, context, json)| (context, json))
+            .collect();
+    }
+}
+
+/// Collects entries across contexts, pods and containers into groups by a field
+/// of their JSON message (`--group-by`), optionally only one value of it (`--trace`).
+///
+/// Without a window (no-follow mode) groups are released only by `pop_all` once every stream
+/// has ended, with a window (follow mode) a group is released once it got no entry for the window.
+pub struct EntryGrouper {
+    pub field: String,
+    only_value: Option<String>,
+    window: Option<Duration>,
+    groups: BTreeMap<u64, EntryGroup>, // in order of the first entry
+    sequences: HashMap<String, u64>,
+    sequence: u64,
+}
+
+impl EntryGrouper {
+    pub fn new(field: &str, only_value: Option<String>, window: Option<Duration>) -> Self {
+        return EntryGrouper {
+            field: field.to_string(),
+            only_value,
+            window,
+            groups: BTreeMap::new(),
+            sequences: HashMap::new(),
+            sequence: 0,
+        };
+    }
+
+    pub fn accepts(&self, value: &str) -> bool {
+        return match &self.only_value {
+            Some(only_value) => only_value == value,
+            None => true,
+        };
+    }
+
+    pub fn push(&mut self, value: &str, context: &str, json: SternJSON) {
+        let sequence = *self.sequences.entry(value.to_string()).or_insert_with(|| {
+            self.sequence += 1;
+            self.sequence
+        });
+
+        let group = self.groups.entry(sequence).or_insert_with(|| EntryGroup {
+            value: value.to_string(),
+            contexts: vec![],
+            entries: vec![],
+            last_arrival: Instant::now(),
+        });
+
+        if !group.contexts.iter().any(|icontext| icontext == context) {
+            group.contexts.push(context.to_string());
+        }
+
+        group.entries.push((context.to_string(), json));
+        group.last_arrival = Instant::now();
+    }
+
+    /// Returns groups which got no entry for the window.
+    pub fn pop_ready(&mut self) -> Vec<EntryGroup> {
+        let window = match self.window {
+            Some(window) => window,
+            None => return vec![],
+        };
+
+        let ready_sequences: Vec<u64> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| group.last_arrival.elapsed() >= window)
+            .map(|(sequence, _)| *sequence)
+            .collect();
+
+        return ready_sequences
+            .into_iter()
+            .filter_map(|sequence| self.pop_group(sequence))
+            .collect();
+    }
+
+    /// Returns all pending groups.
+    pub fn pop_all(&mut self) -> Vec<EntryGroup> {
+        let sequences: Vec<u64> = self.groups.keys().cloned().collect();
+
+        return sequences
+            .into_iter()
+            .filter_map(|sequence| self.pop_group(sequence))
+            .collect();
+    }
+
+    fn pop_group(&mut self, sequence: u64) -> Option<EntryGroup> {
+        let group = self.groups.remove(&sequence)?;
+
+        self.sequences.remove(&group.value);
+
+        return Some(group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn new_json(message: &str, second: Option<u32>) -> SternJSON {
+        let mut json = SternJSON::from_fields(message, message, "node", "ns", "pod", "app", None);
+
+        json.datetime =
+            second.map(|second| Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap());
+
+        return json;
+    }
+
+    fn get_timeline(group: EntryGroup) -> Vec<String> {
+        return group
+            .into_timeline()
+            .into_iter()
+            .map(|(context, json)| format!("{}:{}", context, json.message))
+            .collect();
+    }
+
+    #[test]
+    fn test_accepts() {
+        assert!(EntryGrouper::new("request_id", None, None).accepts("abc"));
+        assert!(EntryGrouper::new("request_id", Some("abc".into()), None).accepts("abc"));
+        assert!(!EntryGrouper::new("request_id", Some("abc".into()), None).accepts("xyz"));
+    }
+
+    #[test]
+    fn test_pop_all() {
+        let mut grouper = EntryGrouper::new("request_id", None, None);
+
+        grouper.push("r2", "c1", new_json("r2 first", Some(5)));
+        grouper.push("r1", "c2", new_json("r1 first", Some(1)));
+        grouper.push("r2", "c2", new_json("r2 second", Some(6)));
+        grouper.push("r2", "c1", new_json("r2 third", Some(7)));
+
+        // without a window nothing is released before the end of the streams
+        assert!(grouper.pop_ready().is_empty());
+
+        let groups = grouper.pop_all();
+
+        // in order of the first entry of each group
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].value, "r2");
+        assert_eq!(groups[0].contexts, vec!["c1", "c2"]);
+        assert_eq!(groups[1].value, "r1");
+        assert_eq!(groups[1].contexts, vec!["c2"]);
+        assert!(grouper.pop_all().is_empty());
+
+        // the value starts a new group once its group was released
+        grouper.push("r2", "c1", new_json("r2 later", Some(9)));
+
+        let groups = grouper.pop_all();
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].entries.len(), 1);
+    }
+
+    #[test]
+    fn test_pop_ready() {
+        let mut grouper = EntryGrouper::new("request_id", None, Some(Duration::ZERO));
+
+        grouper.push("r1", "c1", new_json("r1 first", Some(1)));
+
+        assert_eq!(grouper.pop_ready().len(), 1);
+        assert!(grouper.pop_ready().is_empty());
+
+        let mut grouper = EntryGrouper::new("request_id", None, Some(Duration::from_secs(60)));
+
+        grouper.push("r1", "c1", new_json("r1 first", Some(1)));
+
+        assert!(grouper.pop_ready().is_empty());
+    }
+
+    #[test]
+    fn test_into_timeline() {
+        let mut grouper = EntryGrouper::new("request_id", None, None);
+
+        grouper.push("r1", "c1", new_json("third", Some(3)));
+        grouper.push("r1", "c1", new_json("after third", None));
+        grouper.push("r1", "c2", new_json("first", Some(1)));
+        grouper.push("r1", "c2", new_json("also third", Some(3)));
+
+        // entries without a timestamp stay after the entry which preceded them,
+        // ties keep the order in which the entries came
+        assert_eq!(
+            get_timeline(grouper.pop_all().remove(0)),
+            vec!["c2:first", "c1:third", "c1:after third", "c2:also third"]
+        );
+    }
+}
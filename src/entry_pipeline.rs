@@ -0,0 +1,15 @@
+use crate::entry_grouper::EntryGrouper;
+use crate::log_merger::LogMerger;
+use crate::message_regex::MessageRegEx;
+use crate::multi_line_joiner::MultiLineJoiner;
+use crate::print_options::PrintOptions;
+
+/// Stages which parsed entries pass before they are printed, each one only if enabled:
+/// joining stack trace lines, merging by timestamp and grouping by a field.
+pub struct EntryPipeline<'a> {
+    pub print_options: &'a PrintOptions,
+    pub message_regex: &'a MessageRegEx,
+    pub joiner: Option<MultiLineJoiner>,
+    pub merger: Option<LogMerger>,
+    pub grouper: Option<EntryGrouper>,
+}
@@ -0,0 +1,297 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+
+// number of the innermost frames which are a part of the fingerprint
+const TOP_FRAMES: usize = 3;
+
+const PYTHON_TRACEBACK: &str = "Traceback (most recent call last):";
+const PYTHON_FRAME: &str = r#"^\s*File "(?P<file>[^"]+)", line \d+, in (?P<function>\S+)"#;
+const PYTHON_EXCEPTION: &str = r"^(?P<type>[\w.]+(?:Error|Exception|Warning|Exit|Interrupt))\b";
+const JAVA_FRAME: &str = r"^\s*at (?P<function>[\w$.<>/]+)\(";
+const JAVA_EXCEPTION: &str = r"(?P<type>(?:[\w$]+\.)+[\w$]*(?:Exception|Error|Throwable))\b";
+const GO_PANIC: &str = r"^panic: ";
+const GO_FUNCTION: &str = r"^(?:created by )?[\w./*()-]+\(.*\)(?: in goroutine \d+)?$";
+const NUMBERS: &str = r"0x[0-9a-fA-F]+|\d+";
+
+/// Occurrences of one exception fingerprint.
+pub struct ExceptionGroup {
+    pub exception_type: String,
+    pub count: u128,
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub contexts: Vec<String>,
+    pub sample_message: String,
+}
+
+/// Groups exceptions by their fingerprint: the exception type plus the normalized
+/// innermost frames of the stack trace (file and function names, without line numbers).
+pub struct ExceptionSummary {
+    pub groups: HashMap<String, ExceptionGroup>,
+    python_frame: Regex,
+    python_exception: Regex,
+    java_frame: Regex,
+    java_exception: Regex,
+    go_panic: Regex,
+    go_function: Regex,
+    numbers: Regex,
+}
+
+impl ExceptionSummary {
+    pub fn new() -> Self {
+        return ExceptionSummary {
+            groups: HashMap::new(),
+            python_frame: Regex::new(PYTHON_FRAME).unwrap(),
+            python_exception: Regex::new(PYTHON_EXCEPTION).unwrap(),
+            java_frame: Regex::new(JAVA_FRAME).unwrap(),
+            java_exception: Regex::new(JAVA_EXCEPTION).unwrap(),
+            go_panic: Regex::new(GO_PANIC).unwrap(),
+            go_function: Regex::new(GO_FUNCTION).unwrap(),
+            numbers: Regex::new(NUMBERS).unwrap(),
+        };
+    }
+
+    /// Counts an exception (`exc_info` or a joined stack trace) of an entry,
+    /// returns false if the text does not look like a stack trace.
+    pub fn add(
+        &mut self,
+        context: &str,
+        datetime: Option<DateTime<Utc>>,
+        message: &str,
+        exception: &str,
+    ) -> bool {
+        let (exception_type, frames) = match self.fingerprint(exception) {
+            Some(fingerprint) => fingerprint,
+            None => return false,
+        };
+
+        let key = format!("{}|{}", exception_type, frames.join("|"));
+        let sample_message = self.get_sample_message(message);
+
+        let group = self.groups.entry(key).or_insert_with(|| ExceptionGroup {
+            exception_type,
+            count: 0,
+            first_seen: datetime,
+            last_seen: datetime,
+            contexts: vec![],
+            sample_message,
+        });
+
+        group.count += 1;
+
+        if datetime.is_some() {
+            group.first_seen = group.first_seen.min(datetime).or(datetime);
+            group.last_seen = group.last_seen.max(datetime);
+        }
+
+        if !group.contexts.iter().any(|icontext| icontext == context) {
+            group.contexts.push(context.to_string());
+        }
+
+        return true;
+    }
+
+    /// Returns groups ordered from the most frequent.
+    pub fn get_sorted_groups(&self) -> Vec<&ExceptionGroup> {
+        let mut groups: Vec<&ExceptionGroup> = self.groups.values().collect();
+
+        groups.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.exception_type.cmp(&b.exception_type))
+        });
+
+        return groups;
+    }
+
+    /// Returns the first line of the message, or the final exception line of
+    /// a Python traceback, like "ValueError: bad" instead of its header.
+    fn get_sample_message(&self, message: &str) -> String {
+        let first_line = message.lines().next().unwrap_or_default();
+
+        if first_line.trim() != PYTHON_TRACEBACK {
+            return first_line.to_string();
+        }
+
+        return message
+            .lines()
+            .rev()
+            .map(|iline| iline.trim())
+            .find(|iline| self.python_exception.is_match(iline))
+            .unwrap_or(first_line)
+            .to_string();
+    }
+
+    fn fingerprint(&self, exception: &str) -> Option<(String, Vec<String>)> {
+        let lines: Vec<&str> = exception.lines().collect();
+
+        if lines.iter().any(|iline| self.python_frame.is_match(iline)) {
+            return Some(self.fingerprint_python(&lines));
+        }
+
+        if lines.iter().any(|iline| self.java_frame.is_match(iline)) {
+            return Some(self.fingerprint_java(&lines));
+        }
+
+        if lines.iter().any(|iline| self.go_panic.is_match(iline)) {
+            return Some(self.fingerprint_go(&lines));
+        }
+
+        return None;
+    }
+
+    fn fingerprint_python(&self, lines: &[&str]) -> (String, Vec<String>) {
+        let mut frames: Vec<String> = lines
+            .iter()
+            .filter_map(|iline| self.python_frame.captures(iline))
+            .map(|parsed| format!("{}:{}", &parsed["file"], &parsed["function"]))
+            .collect();
+
+        // innermost frames are the last ones
+        frames.drain(..frames.len().saturating_sub(TOP_FRAMES));
+
+        let exception_type = lines
+            .iter()
+            .rev()
+            .find_map(|iline| self.python_exception.captures(iline.trim()))
+            .map(|parsed| parsed["type"].to_string())
+            .unwrap_or_else(|| "Traceback".to_string());
+
+        return (exception_type, frames);
+    }
+
+    fn fingerprint_java(&self, lines: &[&str]) -> (String, Vec<String>) {
+        // innermost frames are the first ones
+        let frames: Vec<String> = lines
+            .iter()
+            .filter_map(|iline| self.java_frame.captures(iline))
+            .take(TOP_FRAMES)
+            .map(|parsed| parsed["function"].to_string())
+            .collect();
+
+        let exception_type = lines
+            .iter()
+            .find_map(|iline| self.java_exception.captures(iline))
+            .map(|parsed| parsed["type"].to_string())
+            .unwrap_or_else(|| "Exception".to_string());
+
+        return (exception_type, frames);
+    }
+
+    fn fingerprint_go(&self, lines: &[&str]) -> (String, Vec<String>) {
+        // innermost frames are the first ones, function lines without arguments
+        let frames: Vec<String> = lines
+            .iter()
+            .map(|iline| iline.trim())
+            .filter(|iline| self.go_function.is_match(iline))
+            .take(TOP_FRAMES)
+            .map(|iline| match iline.rfind('(') {
+                Some(index) => iline[..index].to_string(),
+                None => iline.to_string(),
+            })
+            .collect();
+
+        let exception_type = lines
+            .iter()
+            .find(|iline| self.go_panic.is_match(iline))
+            .map(|iline| self.numbers.replace_all(iline.trim(), "N").to_string())
+            .unwrap_or_else(|| "panic".to_string());
+
+        return (exception_type, frames);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const PYTHON_TRACE: &str = "Traceback (most recent call last):\n  File \"/app/main.py\", line 9, in <module>\n    main()\n  File \"/app/main.py\", line 5, in main\n    raise ValueError(\"bad\")\nValueError: bad";
+    const JAVA_TRACE: &str = "java.lang.IllegalStateException: bad\n\tat com.example.Service.run(Service.java:10)\n\tat com.example.Main.main(Main.java:5)";
+    const GO_TRACE: &str = "panic: runtime error: index out of range [5] with length 3\n\ngoroutine 1 [running]:\nmain.process(0xc000012345)\n\t/app/main.go:12 +0x1d\nmain.main()\n\t/app/main.go:5 +0x25";
+
+    fn new_datetime(second: u32) -> Option<DateTime<Utc>> {
+        return Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let summary = ExceptionSummary::new();
+
+        assert_eq!(
+            summary.fingerprint(PYTHON_TRACE),
+            Some((
+                "ValueError".to_string(),
+                vec![
+                    "/app/main.py:<module>".to_string(),
+                    "/app/main.py:main".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            summary.fingerprint(JAVA_TRACE),
+            Some((
+                "java.lang.IllegalStateException".to_string(),
+                vec![
+                    "com.example.Service.run".to_string(),
+                    "com.example.Main.main".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            summary.fingerprint(GO_TRACE),
+            Some((
+                "panic: runtime error: index out of range [N] with length N".to_string(),
+                vec!["main.process".to_string(), "main.main".to_string()]
+            ))
+        );
+        assert_eq!(summary.fingerprint("just a message"), None);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut summary = ExceptionSummary::new();
+
+        assert!(summary.add("c1", new_datetime(5), JAVA_TRACE, JAVA_TRACE));
+        // other line numbers are the same exception
+        assert!(summary.add(
+            "c2",
+            new_datetime(1),
+            JAVA_TRACE,
+            &JAVA_TRACE.replace("Service.java:10", "Service.java:11"),
+        ));
+        assert!(summary.add("c1", None, JAVA_TRACE, JAVA_TRACE));
+        assert!(summary.add("c1", new_datetime(3), PYTHON_TRACE, PYTHON_TRACE));
+        assert!(!summary.add("c1", new_datetime(3), "no trace", "no trace"));
+
+        let groups = summary.get_sorted_groups();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].exception_type, "java.lang.IllegalStateException");
+        assert_eq!(groups[0].count, 3);
+        assert_eq!(groups[0].first_seen, new_datetime(1));
+        assert_eq!(groups[0].last_seen, new_datetime(5));
+        assert_eq!(groups[0].contexts, vec!["c1", "c2"]);
+        assert_eq!(
+            groups[0].sample_message,
+            "java.lang.IllegalStateException: bad"
+        );
+        assert_eq!(groups[1].exception_type, "ValueError");
+        assert_eq!(groups[1].count, 1);
+    }
+
+    #[test]
+    fn test_get_sample_message() {
+        let summary = ExceptionSummary::new();
+
+        assert_eq!(summary.get_sample_message(PYTHON_TRACE), "ValueError: bad");
+        assert_eq!(
+            summary.get_sample_message("Traceback (most recent call last):\n  File \"x.py\""),
+            "Traceback (most recent call last):"
+        );
+        assert_eq!(
+            summary.get_sample_message("failed: bad\nmore"),
+            "failed: bad"
+        );
+    }
+}
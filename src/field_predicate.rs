@@ -0,0 +1,188 @@
+use anyhow::{Error, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::json_utils::{get_value_by_path, value_to_string};
+
+// two characters operators go first
+const OPERATORS: [&str; 7] = [">=", "<=", "!=", "=", ">", "<", "~"];
+
+enum Operator {
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    LessOrEqual,
+    Greater,
+    Less,
+    Matches(Regex),
+}
+
+/// A `--where` filter on a field of the JSON message, like `response_code>=500`,
+/// `user.id=42` or `path~^/api/v2`.
+///
+/// Numbers are compared as numbers (also when the field is a numeric string),
+/// `true`/`false`/`null` match JSON booleans and null, anything else is compared as string.
+pub struct FieldPredicate {
+    path: String,
+    operator: Operator,
+    value: String,
+}
+
+impl FieldPredicate {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut found = None;
+
+        for (index, _) in s.char_indices() {
+            if let Some(operator) = OPERATORS.iter().find(|op| s[index..].starts_with(*op)) {
+                found = Some((index, *operator));
+                break;
+            }
+        }
+
+        let (index, operator) = match found {
+            Some((index, operator)) if index > 0 => (index, operator),
+            _ => {
+                return Err(Error::msg(format!(
+                    "\"{}\" no field predicate, use <field><operator><value> with one of {}",
+                    s,
+                    OPERATORS.join(" ")
+                )))
+            }
+        };
+
+        let value = s[index + operator.len()..].trim().to_string();
+
+        return Ok(FieldPredicate {
+            path: s[..index].trim().to_string(),
+            operator: match operator {
+                "=" => Operator::Equal,
+                "!=" => Operator::NotEqual,
+                ">=" => Operator::GreaterOrEqual,
+                "<=" => Operator::LessOrEqual,
+                ">" => Operator::Greater,
+                "<" => Operator::Less,
+                _ => Operator::Matches(Regex::new(&value)?),
+            },
+            value,
+        });
+    }
+
+    /// Entries without such field never match.
+    pub fn is_match(&self, internal_json_message: &HashMap<String, Value>) -> bool {
+        let field = match get_value_by_path(internal_json_message, &self.path) {
+            Some(field) => field,
+            None => return false,
+        };
+
+        let ordering = match &self.operator {
+            Operator::Matches(regex) => return regex.is_match(&value_to_string(field)),
+            _ => self.compare(field),
+        };
+
+        return match self.operator {
+            Operator::Equal => ordering == Some(Ordering::Equal),
+            Operator::NotEqual => ordering != Some(Ordering::Equal),
+            Operator::GreaterOrEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+            Operator::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Operator::Greater => ordering == Some(Ordering::Greater),
+            Operator::Less => ordering == Some(Ordering::Less),
+            Operator::Matches(_) => false,
+        };
+    }
+
+    fn compare(&self, field: &Value) -> Option<Ordering> {
+        return match field {
+            Value::Number(number) => number.as_f64()?.partial_cmp(&self.value.parse().ok()?),
+            Value::String(string) => match (string.parse::<f64>(), self.value.parse::<f64>()) {
+                (Ok(number), Ok(value)) => number.partial_cmp(&value),
+                _ => Some(string.as_str().cmp(self.value.as_str())),
+            },
+            Value::Bool(boolean) => Some(boolean.cmp(&self.value.parse().ok()?)),
+            Value::Null if self.value == "null" => Some(Ordering::Equal),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_message(json: &str) -> HashMap<String, Value> {
+        return serde_json::from_str(json).unwrap();
+    }
+
+    fn is_match(predicate: &str, json: &str) -> bool {
+        return FieldPredicate::parse(predicate)
+            .unwrap()
+            .is_match(&new_message(json));
+    }
+
+    #[test]
+    fn test_parse() {
+        let predicate = FieldPredicate::parse(" user.id >= 42 ").unwrap();
+
+        assert_eq!(predicate.path, "user.id");
+        assert!(matches!(predicate.operator, Operator::GreaterOrEqual));
+        assert_eq!(predicate.value, "42");
+
+        // the first operator wins, the rest belongs to the value
+        let predicate = FieldPredicate::parse("query=a>b").unwrap();
+
+        assert_eq!(predicate.path, "query");
+        assert!(matches!(predicate.operator, Operator::Equal));
+        assert_eq!(predicate.value, "a>b");
+
+        assert!(matches!(
+            FieldPredicate::parse("a!=b").unwrap().operator,
+            Operator::NotEqual
+        ));
+        assert!(matches!(
+            FieldPredicate::parse("a<=b").unwrap().operator,
+            Operator::LessOrEqual
+        ));
+        assert!(matches!(
+            FieldPredicate::parse("a~^b").unwrap().operator,
+            Operator::Matches(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(FieldPredicate::parse("response_code").is_err());
+        assert!(FieldPredicate::parse("=500").is_err());
+        assert!(FieldPredicate::parse("path~(").is_err());
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert!(is_match("code>=500", r#"{"code": 503}"#));
+        assert!(!is_match("code>=500", r#"{"code": 200}"#));
+        assert!(is_match("code=500", r#"{"code": "500"}"#));
+        assert!(is_match("duration>999.5", r#"{"duration": 1000}"#));
+        assert!(!is_match("code<abc", r#"{"code": 1}"#));
+    }
+
+    #[test]
+    fn test_strings_booleans_and_null() {
+        assert!(is_match("level=error", r#"{"level": "error"}"#));
+        assert!(is_match("level!=error", r#"{"level": "info"}"#));
+        assert!(is_match("cached=true", r#"{"cached": true}"#));
+        assert!(!is_match("cached=true", r#"{"cached": false}"#));
+        assert!(is_match("user=null", r#"{"user": null}"#));
+        assert!(!is_match("user=null", r#"{"user": "null-user"}"#));
+    }
+
+    #[test]
+    fn test_paths_and_regex() {
+        assert!(is_match("user.id=42", r#"{"user": {"id": 42}}"#));
+        assert!(is_match("path~^/api/v2", r#"{"path": "/api/v2/users"}"#));
+        assert!(!is_match("path~^/api/v2", r#"{"path": "/api/v1/users"}"#));
+        assert!(!is_match("missing=1", r#"{"code": 1}"#));
+        assert!(!is_match("missing!=1", r#"{"code": 1}"#));
+    }
+}
@@ -1,5 +1,17 @@
 use anyhow::Result;
-use std::{fs::File, io::Write};
+use std::{
+    fs::File,
+    io::{stdin, BufRead, BufReader, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Reserves stdout for machine-readable output (see `my_println_reserved`),
+/// everything printed by `my_println` goes to stderr instead.
+pub fn set_stdout_reserved(reserved: bool) {
+    STDOUT_RESERVED.store(reserved, Ordering::Relaxed);
+}
 
 // TODO make it like println! macro
 pub fn my_println(
@@ -7,6 +19,30 @@ pub fn my_println(
     write_log: &bool,
     write_stdout: &bool,
     s: &String,
+) -> Result<()> {
+    if *write_stdout {
+        if STDOUT_RESERVED.load(Ordering::Relaxed) {
+            eprintln!("{}", s);
+        } else {
+            println!("{}", s);
+        }
+    }
+
+    if *write_log {
+        if let Some(log_handle) = log_handle {
+            log_handle.write_fmt(format_args!("{}\n", s))?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// Like `my_println` but always writes to stdout, even when it is reserved.
+pub fn my_println_reserved(
+    log_handle: &mut Option<File>,
+    write_log: &bool,
+    write_stdout: &bool,
+    s: &String,
 ) -> Result<()> {
     if *write_stdout {
         println!("{}", s);
@@ -20,3 +56,12 @@ pub fn my_println(
 
     return Ok(());
 }
+
+/// Opens a file for buffered reading, "-" means stdin.
+pub fn open_buffered_reader(pathname: &str) -> Result<Box<dyn BufRead>> {
+    if pathname == "-" {
+        return Ok(Box::new(BufReader::new(stdin())));
+    }
+
+    return Ok(Box::new(BufReader::new(File::open(pathname)?)));
+}
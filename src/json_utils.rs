@@ -29,3 +29,41 @@ pub fn json_to_hashmap(json: &str) -> Result<HashMap<String, Value>> {
 
     return Ok(parsed);
 }
+
+/// Gets a value from a parsed JSON object by a dotted path like `user.id` or `items.0.name`.
+///
+/// A key which itself contains dots (e.g. `log.level`) is matched first, before
+/// the path is split into nested objects and array indexes.
+///
+/// # Examples
+///
+/// ```
+/// let parsed = json_to_hashmap("{\"user\":{\"id\":42}}").unwrap();
+/// assert_eq!(get_value_by_path(&parsed, "user.id"), Some(&Value::from(42)));
+/// ```
+pub fn get_value_by_path<'a>(hashmap: &'a HashMap<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = hashmap.get(path) {
+        return Some(value);
+    }
+
+    let (first, rest) = path.split_once(".")?;
+    let mut value = hashmap.get(first)?;
+
+    for ikey in rest.split(".") {
+        value = match value {
+            Value::Object(object) => object.get(ikey)?,
+            Value::Array(array) => array.get(ikey.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    return Some(value);
+}
+
+/// Converts a JSON value to a string to be printed, strings are printed without quotes.
+pub fn value_to_string(value: &Value) -> String {
+    return match value {
+        Value::String(s) => s.to_string(),
+        _ => value.to_string(),
+    };
+}
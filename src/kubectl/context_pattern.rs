@@ -0,0 +1,362 @@
+use anyhow::{Error, Result};
+use regex::Regex;
+
+use super::Context;
+use crate::string_utils::levenshtein;
+
+/// A value of `--context` or `--exclude-context`: a context name, a glob
+/// like `prod-*` or a regex between slashes like `/^stg-(eu|us)/`.
+pub enum ContextPattern {
+    All,
+    Name(String),
+    Glob(String, Regex),
+    Regex(String, Regex),
+}
+
+impl ContextPattern {
+    pub fn parse(s: &str) -> Result<Self> {
+        if s == "all" {
+            return Ok(ContextPattern::All);
+        }
+
+        if s.len() > 1 && s.starts_with('/') && s.ends_with('/') {
+            return match Regex::new(&s[1..s.len() - 1]) {
+                Ok(regex) => Ok(ContextPattern::Regex(s.to_string(), regex)),
+                Err(err) => Err(Error::msg(format!(
+                    "invalid context regex \"{}\" ({})",
+                    s, err
+                ))),
+            };
+        }
+
+        if s.contains(['*', '?', '[']) {
+            return Ok(ContextPattern::Glob(
+                s.to_string(),
+                Regex::new(&Self::glob_to_regex(s))?,
+            ));
+        }
+
+        return Ok(ContextPattern::Name(s.to_string()));
+    }
+
+    pub fn parse_list(patterns: &[String]) -> Result<Vec<Self>> {
+        return patterns
+            .iter()
+            .map(|ipattern| Self::parse(ipattern))
+            .collect();
+    }
+
+    pub fn is_name(&self) -> bool {
+        return matches!(self, ContextPattern::Name(_));
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        return match self {
+            ContextPattern::All => true,
+            ContextPattern::Name(pattern_name) => pattern_name == name,
+            ContextPattern::Glob(_, regex) | ContextPattern::Regex(_, regex) => {
+                regex.is_match(name)
+            }
+        };
+    }
+
+    pub fn as_str(&self) -> &str {
+        return match self {
+            ContextPattern::All => "all",
+            ContextPattern::Name(pattern)
+            | ContextPattern::Glob(pattern, _)
+            | ContextPattern::Regex(pattern, _) => pattern,
+        };
+    }
+
+    fn glob_to_regex(glob: &str) -> String {
+        let mut regex = String::from("^");
+        let mut in_class = false;
+
+        for ichar in glob.chars() {
+            match ichar {
+                '*' if !in_class => regex.push_str(".*"),
+                '?' if !in_class => regex.push('.'),
+                '[' if !in_class => {
+                    in_class = true;
+                    regex.push('[');
+                }
+                ']' if in_class => {
+                    in_class = false;
+                    regex.push(']');
+                }
+                '!' if in_class && regex.ends_with('[') => regex.push('^'),
+                _ => regex.push_str(&regex::escape(&ichar.to_string())),
+            }
+        }
+
+        regex.push('$');
+
+        return regex;
+    }
+}
+
+/// Selects contexts matching any of `include` patterns and none of `exclude` ones,
+/// in the order of `contexts`; fails if a pattern matches no context.
+///
+/// "default" is the current context unless some context is named so.
+pub fn select_contexts(
+    contexts: &[Context],
+    include: &[ContextPattern],
+    exclude: &[ContextPattern],
+) -> Result<Vec<Context>> {
+    let has_default = contexts.iter().any(|icontext| icontext.name == "default");
+    let is_selected = |pattern: &ContextPattern, context: &Context| -> bool {
+        if !has_default && pattern.as_str() == "default" && pattern.is_name() {
+            return context.current;
+        }
+
+        return pattern.is_match(&context.name);
+    };
+
+    for ipattern in include.iter().chain(exclude.iter()) {
+        if !contexts
+            .iter()
+            .any(|icontext| is_selected(ipattern, icontext))
+        {
+            return Err(get_no_match_error(contexts, ipattern));
+        }
+    }
+
+    let selected: Vec<Context> = contexts
+        .iter()
+        .filter(|icontext| {
+            include
+                .iter()
+                .any(|ipattern| is_selected(ipattern, icontext))
+                && !exclude
+                    .iter()
+                    .any(|ipattern| is_selected(ipattern, icontext))
+        })
+        .cloned()
+        .collect();
+
+    if selected.is_empty() {
+        return Err(Error::msg("all contexts are excluded by --exclude-context"));
+    }
+
+    return Ok(selected);
+}
+
+fn get_no_match_error(contexts: &[Context], pattern: &ContextPattern) -> Error {
+    if !pattern.is_name() {
+        return Error::msg(format!(
+            "context pattern \"{}\" matches no context, available contexts: {}",
+            pattern.as_str(),
+            contexts
+                .iter()
+                .map(|icontext| icontext.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ));
+    }
+
+    if pattern.as_str() == "default" {
+        return Error::msg("no context named \"default\" and no current context in kubeconfig");
+    }
+
+    // close enough names, like "stagin" for "staging"
+    let max_distance = (pattern.as_str().chars().count() / 3).max(2);
+    let mut suggestions: Vec<(usize, &str)> = contexts
+        .iter()
+        .map(|icontext| {
+            (
+                levenshtein(pattern.as_str(), &icontext.name),
+                icontext.name.as_str(),
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    suggestions.sort();
+
+    if suggestions.is_empty() {
+        return Error::msg(format!(
+            "context \"{}\" not found, available contexts: {}",
+            pattern.as_str(),
+            contexts
+                .iter()
+                .map(|icontext| icontext.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ));
+    }
+
+    return Error::msg(format!(
+        "context \"{}\" not found, did you mean {}?",
+        pattern.as_str(),
+        suggestions
+            .iter()
+            .take(3)
+            .map(|(_, name)| format!("\"{}\"", name))
+            .collect::<Vec<String>>()
+            .join(" or ")
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_contexts(names: &[&str]) -> Vec<Context> {
+        return new_contexts_with_current(names, "");
+    }
+
+    fn new_contexts_with_current(names: &[&str], current: &str) -> Vec<Context> {
+        return names
+            .iter()
+            .map(|iname| Context {
+                current: *iname == current,
+                name: iname.to_string(),
+                cluster: String::new(),
+                auth_info: String::new(),
+                namespace: String::new(),
+            })
+            .collect();
+    }
+
+    fn get_selected_names(
+        contexts: &[Context],
+        include: &[&str],
+        exclude: &[&str],
+    ) -> Result<Vec<String>> {
+        let to_patterns = |patterns: &[&str]| {
+            ContextPattern::parse_list(
+                &patterns
+                    .iter()
+                    .map(|ipattern| ipattern.to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        return Ok(
+            select_contexts(contexts, &to_patterns(include)?, &to_patterns(exclude)?)?
+                .into_iter()
+                .map(|icontext| icontext.name)
+                .collect(),
+        );
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        assert_eq!(ContextPattern::glob_to_regex("prod-*"), "^prod\\-.*$");
+        assert_eq!(ContextPattern::glob_to_regex("a.b?"), "^a\\.b.$");
+        assert_eq!(ContextPattern::glob_to_regex("stg-[eu]*"), "^stg\\-[eu].*$");
+        assert_eq!(ContextPattern::glob_to_regex("[!x]"), "^[^x]$");
+        assert_eq!(ContextPattern::glob_to_regex("[*?]"), "^[\\*\\?]$");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(matches!(
+            ContextPattern::parse("all").unwrap(),
+            ContextPattern::All
+        ));
+        assert!(ContextPattern::parse("staging").unwrap().is_name());
+
+        let glob = ContextPattern::parse("prod-*").unwrap();
+
+        assert!(glob.is_match("prod-eu"));
+        assert!(!glob.is_match("preprod-eu"));
+
+        let regex = ContextPattern::parse("/^stg-(eu|us)/").unwrap();
+
+        assert!(regex.is_match("stg-eu-1"));
+        assert!(!regex.is_match("stg-asia"));
+        assert_eq!(regex.as_str(), "/^stg-(eu|us)/");
+
+        assert!(ContextPattern::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn test_select_contexts() {
+        let contexts = new_contexts(&["prod-eu", "prod-us", "staging"]);
+
+        assert_eq!(
+            get_selected_names(&contexts, &["prod-*"], &[]).unwrap(),
+            vec!["prod-eu", "prod-us"]
+        );
+        assert_eq!(
+            get_selected_names(&contexts, &["all"], &["prod-us"]).unwrap(),
+            vec!["prod-eu", "staging"]
+        );
+        assert_eq!(
+            get_selected_names(&contexts, &["/-(eu|us)$/"], &["/us/"]).unwrap(),
+            vec!["prod-eu"]
+        );
+    }
+
+    #[test]
+    fn test_select_contexts_errors() {
+        let contexts = new_contexts(&["prod", "staging"]);
+
+        assert_eq!(
+            get_selected_names(&contexts, &["all"], &["dev-*"])
+                .unwrap_err()
+                .to_string(),
+            "context pattern \"dev-*\" matches no context, available contexts: prod, staging"
+        );
+        assert_eq!(
+            get_selected_names(&contexts, &["all"], &["prod", "staging"])
+                .unwrap_err()
+                .to_string(),
+            "all contexts are excluded by --exclude-context"
+        );
+    }
+
+    #[test]
+    fn test_select_default_context() {
+        let contexts = new_contexts_with_current(&["prod", "staging"], "staging");
+
+        assert_eq!(
+            get_selected_names(&contexts, &["default"], &[]).unwrap(),
+            vec!["staging"]
+        );
+
+        // a context named "default" wins over the current one
+        let contexts = new_contexts_with_current(&["default", "staging"], "staging");
+
+        assert_eq!(
+            get_selected_names(&contexts, &["default"], &[]).unwrap(),
+            vec!["default"]
+        );
+
+        let contexts = new_contexts(&["prod", "staging"]);
+
+        assert_eq!(
+            get_selected_names(&contexts, &["default"], &[])
+                .unwrap_err()
+                .to_string(),
+            "no context named \"default\" and no current context in kubeconfig"
+        );
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let contexts = new_contexts(&["prod-eu", "prod-us", "staging"]);
+
+        assert_eq!(
+            get_selected_names(&contexts, &["stagin"], &[])
+                .unwrap_err()
+                .to_string(),
+            "context \"stagin\" not found, did you mean \"staging\"?"
+        );
+        assert_eq!(
+            get_selected_names(&contexts, &["all"], &["prod-e"])
+                .unwrap_err()
+                .to_string(),
+            "context \"prod-e\" not found, did you mean \"prod-eu\" or \"prod-us\"?"
+        );
+        assert_eq!(
+            get_selected_names(&contexts, &["development"], &[])
+                .unwrap_err()
+                .to_string(),
+            "context \"development\" not found, available contexts: prod-eu, prod-us, staging"
+        );
+    }
+}
@@ -1,20 +1,18 @@
-use std::fs::File;
+use serde_yaml::Value;
+use std::env::{split_paths, var_os};
+use std::fs::{read_to_string, File};
+use std::path::PathBuf;
 
 use super::consts::BINARY_KUBECTL;
 use super::Context;
 use crate::command_streamer::MultiCommandStreamer;
 use crate::file_utils::my_println;
-use crate::string_utils::{lines_check_string_exists, table_to_hashmap};
 use anyhow::{Error, Result};
 
 pub struct Kubectl {}
 
 impl Kubectl {
     pub fn get_contexts(log_handle: &mut Option<File>) -> Result<Vec<Context>> {
-        let mut contexts = Vec::new();
-        let mut multi_streamer = MultiCommandStreamer::new_empty();
-        let mut lines = String::new();
-
         my_println(
             log_handle,
             &true,
@@ -22,77 +20,32 @@ impl Kubectl {
             &"Getting Kubernetes contexts".into(),
         )?;
 
-        multi_streamer.add(
-            BINARY_KUBECTL,
-            &vec!["config".into(), "get-contexts".into()],
-            None,
-        )?;
-
-        match multi_streamer.get_all_lines(true, false).remove(0) {
-            Ok(option) => match option {
-                Some(iline) => lines.push_str(&iline),
-                None => {}
-            },
-            Err(e) => return Err(e),
-        }
-
-        if !lines_check_string_exists(&lines, "CURRENT   NAME      CLUSTER   AUTHINFO   NAMESPACE")
-        {
-            return Err(Error::msg(
-                "\"kubectl config get-contexts\" returns no header, cannot get Kubernetes contexts",
-            ));
-        }
+        let mut kubeconfigs = vec![];
 
-        let lines_table = table_to_hashmap(&lines, "N/A");
-
-        for irow in lines_table {
-            if !irow.contains_key("CURRENT")
-                || !irow.contains_key("NAME")
-                || !irow.contains_key("CLUSTER")
-                || !irow.contains_key("AUTHINFO")
-                || !irow.contains_key("NAMESPACE")
-            {
+        for ipathname in Self::get_kubeconfig_pathnames() {
+            if !ipathname.is_file() {
                 continue;
             }
 
-            let current = irow.get("CURRENT").unwrap() == "*";
-            let mut name = irow.get("NAME").unwrap().to_string().trim().to_string();
-            let mut cluster = irow.get("CLUSTER").unwrap().to_string().trim().to_string();
-            let mut auth_info = irow.get("AUTHINFO").unwrap().to_string().trim().to_string();
-            let mut namespace = irow
-                .get("NAMESPACE")
-                .unwrap()
-                .to_string()
-                .trim()
-                .to_string();
-
-            if name == "N/A" {
-                name = "".to_string();
-            }
-
-            if cluster == "N/A" {
-                cluster = "".to_string();
+            match serde_yaml::from_str(&read_to_string(&ipathname)?) {
+                Ok(kubeconfig) => kubeconfigs.push(kubeconfig),
+                Err(err) => {
+                    return Err(Error::msg(format!(
+                        "cannot parse kubeconfig \"{}\" ({})",
+                        ipathname.display(),
+                        err
+                    )))
+                }
             }
+        }
 
-            if auth_info == "N/A" {
-                auth_info = "".to_string();
-            }
-
-            if namespace == "N/A" {
-                namespace = "".to_string();
-            }
-
-            let context = Context {
-                current,
-                name,
-                cluster,
-                auth_info,
-                namespace,
-            };
-
-            contexts.push(context);
+        if kubeconfigs.is_empty() {
+            // no kubeconfig file, let kubectl resolve its config
+            kubeconfigs.push(Self::get_config_view()?);
         }
 
+        let contexts = Self::merge_contexts(&kubeconfigs);
+
         if !contexts.is_empty() {
             my_println(
                 log_handle,
@@ -124,4 +77,176 @@ impl Kubectl {
 
         return Ok(contexts);
     }
+
+    /// Returns true if some kubeconfig file exists, otherwise contexts are read with kubectl.
+    pub fn has_kubeconfig() -> bool {
+        return Self::get_kubeconfig_pathnames()
+            .iter()
+            .any(|ipathname| ipathname.is_file());
+    }
+
+    /// Returns files listed in `$KUBECONFIG` or `~/.kube/config`, like kubectl does.
+    fn get_kubeconfig_pathnames() -> Vec<PathBuf> {
+        if let Some(kubeconfig) = var_os("KUBECONFIG") {
+            let pathnames: Vec<PathBuf> = split_paths(&kubeconfig)
+                .filter(|ipathname| !ipathname.as_os_str().is_empty())
+                .collect();
+
+            if !pathnames.is_empty() {
+                return pathnames;
+            }
+        }
+
+        return match var_os("HOME").or_else(|| var_os("USERPROFILE")) {
+            Some(home) => vec![PathBuf::from(home).join(".kube").join("config")],
+            None => vec![],
+        };
+    }
+
+    fn get_config_view() -> Result<Value> {
+        let mut multi_streamer = MultiCommandStreamer::new_empty();
+
+        multi_streamer.add(
+            BINARY_KUBECTL,
+            &vec![
+                "config".into(),
+                "view".into(),
+                "--output".into(),
+                "json".into(),
+            ],
+            None,
+        )?;
+
+        // warnings on stderr are not a part of the config
+        let (lines, stderr_lines) = multi_streamer.get_all_lines(true, false).remove(0)?;
+
+        return match serde_yaml::from_str(&lines.unwrap_or_default()) {
+            Ok(config_view) => Ok(config_view),
+            Err(err) => match stderr_lines {
+                Some(stderr_lines) if !stderr_lines.trim().is_empty() => Err(Error::msg(format!(
+                    "cannot parse output of \"kubectl config view\" ({}): {}",
+                    err,
+                    stderr_lines.trim()
+                ))),
+                _ => Err(Error::msg(format!(
+                    "cannot parse output of \"kubectl config view\" ({})",
+                    err
+                ))),
+            },
+        };
+    }
+
+    /// Merges contexts of many kubeconfigs, the first one which sets
+    /// a context (or the current context) wins, like in kubectl.
+    fn merge_contexts(kubeconfigs: &[Value]) -> Vec<Context> {
+        let mut contexts: Vec<Context> = vec![];
+
+        let current_context = kubeconfigs
+            .iter()
+            .filter_map(|ikubeconfig| ikubeconfig["current-context"].as_str())
+            .find(|icurrent_context| !icurrent_context.is_empty())
+            .unwrap_or_default();
+
+        for ikubeconfig in kubeconfigs {
+            let kubeconfig_contexts = match ikubeconfig["contexts"].as_sequence() {
+                Some(kubeconfig_contexts) => kubeconfig_contexts,
+                None => continue,
+            };
+
+            for icontext in kubeconfig_contexts {
+                let name = icontext["name"].as_str().unwrap_or_default().trim();
+
+                if name.is_empty() || contexts.iter().any(|context| context.name == name) {
+                    continue;
+                }
+
+                let get_field = |field: &str| {
+                    icontext["context"][field]
+                        .as_str()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string()
+                };
+
+                contexts.push(Context {
+                    current: name == current_context,
+                    name: name.to_string(),
+                    cluster: get_field("cluster"),
+                    auth_info: get_field("user"),
+                    namespace: get_field("namespace"),
+                });
+            }
+        }
+
+        return contexts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_kubeconfig(yaml: &str) -> Value {
+        return serde_yaml::from_str(yaml).unwrap();
+    }
+
+    #[test]
+    fn test_merge_contexts() {
+        let first = parse_kubeconfig(
+            "
+current-context: staging
+contexts:
+- name: staging
+  context: {cluster: stg, user: admin, namespace: apps}
+- name: ' prod '
+  context: {cluster: prd}
+- name: ''
+  context: {cluster: none}
+",
+        );
+        let second = parse_kubeconfig(
+            "
+current-context: prod
+contexts:
+- name: prod
+  context: {cluster: other}
+- name: dev
+  context: {cluster: dev}
+",
+        );
+
+        let contexts = Kubectl::merge_contexts(&[first, second]);
+        let names: Vec<&str> = contexts
+            .iter()
+            .map(|icontext| icontext.name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["staging", "prod", "dev"]);
+
+        // the first kubeconfig which sets a context or the current context wins
+        assert!(contexts[0].current);
+        assert!(!contexts[1].current);
+        assert_eq!(contexts[0].cluster, "stg");
+        assert_eq!(contexts[0].auth_info, "admin");
+        assert_eq!(contexts[0].namespace, "apps");
+        assert_eq!(contexts[1].cluster, "prd");
+        assert_eq!(contexts[1].namespace, "");
+    }
+
+    #[test]
+    fn test_merge_contexts_without_current() {
+        let kubeconfig = parse_kubeconfig(
+            "
+current-context: ''
+contexts:
+- name: prod
+  context: {cluster: prd}
+",
+        );
+
+        let contexts = Kubectl::merge_contexts(&[kubeconfig, parse_kubeconfig("{}")]);
+
+        assert_eq!(contexts.len(), 1);
+        assert!(!contexts[0].current);
+    }
 }
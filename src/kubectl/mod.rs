@@ -1,4 +1,5 @@
 pub mod context;
+pub mod context_pattern;
 pub mod kubectl;
 
 mod consts;
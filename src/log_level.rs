@@ -0,0 +1,97 @@
+use anyhow::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Normalized severity of a log entry, ordered from the least to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    /// Parses a level name or any of its common aliases, case insensitive.
+    pub fn parse(s: &str) -> Option<Self> {
+        return match s.trim().to_lowercase().as_str() {
+            "trace" | "trc" | "verbose" => Some(LogLevel::Trace),
+            "debug" | "dbg" | "dbug" => Some(LogLevel::Debug),
+            "info" | "inf" | "information" | "informational" | "notice" => Some(LogLevel::Info),
+            "warn" | "wrn" | "warning" => Some(LogLevel::Warn),
+            "error" | "err" | "eror" => Some(LogLevel::Error),
+            "fatal" | "ftl" | "crit" | "critical" | "panic" | "alert" | "emerg" | "emergency" => {
+                Some(LogLevel::Fatal)
+            }
+            _ => None,
+        };
+    }
+
+    /// Parses a level given on the command line.
+    pub fn parse_arg(s: &str) -> Result<Self> {
+        return match Self::parse(s) {
+            Some(level) => Ok(level),
+            None => Err(Error::msg(format!(
+                "\"{}\" no log level, use \"trace\", \"debug\", \"info\", \"warn\", \"error\" or \"fatal\"",
+                s
+            ))),
+        };
+    }
+
+    /// Maps klog's one letter severity (`I0902 12:58:52.123456 ...`).
+    pub fn from_klog_letter(s: &str) -> Option<Self> {
+        return match s {
+            "I" => Some(LogLevel::Info),
+            "W" => Some(LogLevel::Warn),
+            "E" => Some(LogLevel::Error),
+            "F" => Some(LogLevel::Fatal),
+            _ => None,
+        };
+    }
+
+    /// Takes the level from `level`, `severity` or `levelname` field of a JSON message,
+    /// numeric levels are bunyan/pino ones (10 trace ... 60 fatal).
+    pub fn from_json_message(internal_json_message: &HashMap<String, Value>) -> Option<Self> {
+        for ikey in ["level", "severity", "levelname"] {
+            let level = match internal_json_message.get(ikey) {
+                Some(Value::String(value)) => Self::parse(value),
+                Some(Value::Number(value)) => match value.as_u64() {
+                    Some(0..=10) => Some(LogLevel::Trace),
+                    Some(11..=20) => Some(LogLevel::Debug),
+                    Some(21..=30) => Some(LogLevel::Info),
+                    Some(31..=40) => Some(LogLevel::Warn),
+                    Some(41..=50) => Some(LogLevel::Error),
+                    Some(_) => Some(LogLevel::Fatal),
+                    None => None,
+                },
+                _ => None,
+            };
+
+            if level.is_some() {
+                return level;
+            }
+        }
+
+        return None;
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Fatal => "fatal",
+        };
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.as_str());
+    }
+}
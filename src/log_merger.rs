@@ -0,0 +1,244 @@
+use chrono::{DateTime, Utc};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::stern_json::SternJSON;
+
+type MergeKey = (Option<DateTime<Utc>>, u64);
+
+struct MergeEntry {
+    key: MergeKey,
+    context: String,
+    json: SternJSON,
+}
+
+/// Orders log entries from many contexts, pods and containers by their timestamps.
+///
+/// Entries are queued per (context, namespace, pod, container), since each container's
+/// log is already in order, and merged by a k-way merge over the queue heads.
+/// With a reorder window (follow mode) an entry is released once the oldest pending
+/// entry has waited for the window, without one (no-follow mode) entries are released
+/// only by `pop_all` once every stream has ended.
+pub struct LogMerger {
+    window: Option<Duration>,
+    queues: HashMap<String, VecDeque<MergeEntry>>,
+    heads: BinaryHeap<Reverse<(MergeKey, String)>>,
+    last_datetimes: HashMap<String, DateTime<Utc>>,
+    arrivals: BTreeMap<u64, Instant>,
+    sequence: u64,
+}
+
+impl LogMerger {
+    pub fn new(window: Option<Duration>) -> Self {
+        return LogMerger {
+            window,
+            queues: HashMap::new(),
+            heads: BinaryHeap::new(),
+            last_datetimes: HashMap::new(),
+            arrivals: BTreeMap::new(),
+            sequence: 0,
+        };
+    }
+
+    pub fn push(&mut self, context: &str, parsed_lines: Vec<SternJSON>) {
+        let arrival = Instant::now();
+
+        for json in parsed_lines {
+            let queue_key = format!(
+                "{}/{}/{}/{}",
+                context, json.namespace, json.pod_name, json.container_name
+            );
+
+            // entries without a timestamp (e.g. invalid ones) stay right after
+            // the previous entry of the same queue or at least of the same context
+            let datetime = match json.datetime {
+                Some(datetime) => {
+                    self.last_datetimes.insert(queue_key.to_string(), datetime);
+                    self.last_datetimes.insert(context.to_string(), datetime);
+                    Some(datetime)
+                }
+                None => match self.last_datetimes.get(&queue_key) {
+                    Some(datetime) => Some(*datetime),
+                    None => self.last_datetimes.get(context).cloned(),
+                },
+            };
+
+            let key = (datetime, self.sequence);
+            let queue = self.queues.entry(queue_key.to_string()).or_default();
+
+            if queue.is_empty() {
+                self.heads.push(Reverse((key, queue_key)));
+            }
+
+            queue.push_back(MergeEntry {
+                key,
+                context: context.to_string(),
+                json,
+            });
+
+            self.arrivals.insert(self.sequence, arrival);
+            self.sequence += 1;
+        }
+    }
+
+    /// Returns entries which can be printed now, in timestamp order.
+    pub fn pop_ready(&mut self) -> Vec<(String, SternJSON)> {
+        let mut ready = vec![];
+
+        let window = match self.window {
+            Some(window) => window,
+            None => return ready,
+        };
+
+        while let Some((_, arrival)) = self.arrivals.first_key_value() {
+            if arrival.elapsed() < window {
+                break;
+            }
+
+            match self.pop_head() {
+                Some(entry) => ready.push(entry),
+                None => break,
+            }
+        }
+
+        return ready;
+    }
+
+    /// Returns all pending entries in timestamp order.
+    pub fn pop_all(&mut self) -> Vec<(String, SternJSON)> {
+        let mut ready = vec![];
+
+        while let Some(entry) = self.pop_head() {
+            ready.push(entry);
+        }
+
+        return ready;
+    }
+
+    fn pop_head(&mut self) -> Option<(String, SternJSON)> {
+        let Reverse((_, queue_key)) = self.heads.pop()?;
+        let queue = self.queues.get_mut(&queue_key)?;
+        let entry = queue.pop_front()?;
+
+        if let Some(next) = queue.front() {
+            self.heads.push(Reverse((next.key, queue_key)));
+        } else {
+            self.queues.remove(&queue_key);
+        }
+
+        self.arrivals.remove(&entry.key.1);
+
+        return Some((entry.context, entry.json));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn new_json(pod_name: &str, second: Option<u32>, message: &str) -> SternJSON {
+        let mut json =
+            SternJSON::from_fields(message, message, "node", "ns", pod_name, "app", None);
+
+        json.datetime =
+            second.map(|second| Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap());
+
+        return json;
+    }
+
+    fn get_messages(merged: Vec<(String, SternJSON)>) -> Vec<String> {
+        return merged
+            .into_iter()
+            .map(|(context, json)| format!("{}:{}", context, json.message))
+            .collect();
+    }
+
+    #[test]
+    fn test_pop_all_orders_by_timestamp() {
+        let mut merger = LogMerger::new(None);
+
+        merger.push(
+            "c1",
+            vec![new_json("a", Some(1), "a1"), new_json("a", Some(5), "a5")],
+        );
+        merger.push(
+            "c2",
+            vec![new_json("b", Some(3), "b3"), new_json("b", Some(4), "b4")],
+        );
+        merger.push("c1", vec![new_json("c", Some(2), "c2")]);
+
+        assert_eq!(
+            get_messages(merger.pop_all()),
+            vec!["c1:a1", "c1:c2", "c2:b3", "c2:b4", "c1:a5"]
+        );
+        assert!(merger.pop_all().is_empty());
+    }
+
+    #[test]
+    fn test_pop_all_keeps_order_of_ties() {
+        let mut merger = LogMerger::new(None);
+
+        merger.push("c1", vec![new_json("a", Some(1), "a1")]);
+        merger.push("c2", vec![new_json("b", Some(1), "b1")]);
+        merger.push("c1", vec![new_json("a", Some(1), "a2")]);
+
+        // the same timestamp keeps the arrival order
+        assert_eq!(
+            get_messages(merger.pop_all()),
+            vec!["c1:a1", "c2:b1", "c1:a2"]
+        );
+    }
+
+    #[test]
+    fn test_pop_all_keeps_entries_without_timestamp_in_place() {
+        let mut merger = LogMerger::new(None);
+
+        merger.push(
+            "c1",
+            vec![
+                new_json("a", Some(1), "a1"),
+                new_json("a", Some(4), "a4"),
+                new_json("a", None, "invalid"),
+            ],
+        );
+        merger.push("c2", vec![new_json("b", Some(2), "b2")]);
+        merger.push("c2", vec![new_json("b", Some(5), "b5")]);
+
+        assert_eq!(
+            get_messages(merger.pop_all()),
+            vec!["c1:a1", "c2:b2", "c1:a4", "c1:invalid", "c2:b5"]
+        );
+    }
+
+    #[test]
+    fn test_pop_ready() {
+        // without a window nothing is released before the end of the streams
+        let mut merger = LogMerger::new(None);
+
+        merger.push("c1", vec![new_json("a", Some(1), "a1")]);
+
+        assert!(merger.pop_ready().is_empty());
+        assert_eq!(get_messages(merger.pop_all()), vec!["c1:a1"]);
+
+        // entries which waited for the window are released in timestamp order
+        let mut merger = LogMerger::new(Some(Duration::ZERO));
+
+        merger.push(
+            "c1",
+            vec![new_json("a", Some(2), "a2"), new_json("b", Some(1), "b1")],
+        );
+
+        assert_eq!(get_messages(merger.pop_ready()), vec!["c1:b1", "c1:a2"]);
+        assert!(merger.pop_ready().is_empty());
+
+        let mut merger = LogMerger::new(Some(Duration::from_secs(60)));
+
+        merger.push("c1", vec![new_json("a", Some(1), "a1")]);
+
+        assert!(merger.pop_ready().is_empty());
+    }
+}
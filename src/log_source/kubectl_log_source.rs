@@ -0,0 +1,426 @@
+use anyhow::{Error, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::{respawn_since, LogSource, LogSourceOptions, RequiredBinary};
+use crate::command_streamer::{CommandStreamer, MultiCommandStreamer};
+use crate::consts::BINARY_KUBECTL;
+use crate::kubectl::Context;
+use crate::stern_json::SternJSON;
+use crate::stern_json_regex::SternJSONRegEx;
+use crate::string_utils::tokenize_by;
+
+// flags of `kubectl logs` which take a value, also as the next argument
+const LOGS_FLAGS_WITH_VALUE: [&str; 5] = [
+    "--tail",
+    "--since-time",
+    "--limit-bytes",
+    "--max-log-requests",
+    "--pod-running-timeout",
+];
+
+/// A single pod's container streamed by `kubectl logs`.
+struct KubectlLogTarget {
+    context_name: String,
+    node_name: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+}
+
+/// Streams logs by running `kubectl logs` for each pod's container which
+/// matches the pod-query, for hosts where stern is not available.
+///
+/// Supported extra arguments (after `--`) are the pod-query (a regex, like in stern),
+/// `-n, --namespace <namespace>` and `-A, --all-namespaces`; any other flag is passed
+/// to `kubectl logs` as is, as `--flag=value` or, for ones in `LOGS_FLAGS_WITH_VALUE`,
+/// also as `--flag value`.
+///
+/// While following, an exited command is run again only if its pod still exists,
+/// and pods which appear later get their own commands (see `spawn_new`).
+pub struct KubectlLogSource {
+    options: LogSourceOptions,
+    contexts: Vec<Context>,
+    targets: HashMap<String, KubectlLogTarget>,
+}
+
+impl KubectlLogTarget {
+    fn is_same_container(&self, other: &KubectlLogTarget) -> bool {
+        return self.context_name == other.context_name
+            && self.namespace == other.namespace
+            && self.pod_name == other.pod_name
+            && self.container_name == other.container_name;
+    }
+}
+
+impl KubectlLogSource {
+    pub fn new(options: LogSourceOptions) -> Self {
+        return KubectlLogSource {
+            options,
+            contexts: vec![],
+            targets: HashMap::new(),
+        };
+    }
+
+    fn parse_ext_args(&self) -> Result<(Option<String>, bool, String, Vec<String>)> {
+        let mut namespace = None;
+        let mut all_namespaces = self.options.stern_defaults;
+        let mut pod_query = None;
+        let mut logs_args = vec![];
+        let mut ext_args = self.options.ext_args.iter();
+
+        while let Some(iarg) = ext_args.next() {
+            if iarg == "-n" || iarg == "--namespace" {
+                match ext_args.next() {
+                    Some(value) => namespace = Some(value.to_string()),
+                    None => return Err(Error::msg(format!("{} requires a value", iarg))),
+                }
+            } else if let Some(value) = iarg.strip_prefix("--namespace=") {
+                namespace = Some(value.to_string());
+            } else if iarg == "-A" || iarg == "--all-namespaces" {
+                all_namespaces = true;
+            } else if LOGS_FLAGS_WITH_VALUE.contains(&iarg.as_str()) {
+                match ext_args.next() {
+                    Some(value) => {
+                        logs_args.push(iarg.to_string());
+                        logs_args.push(value.to_string());
+                    }
+                    None => return Err(Error::msg(format!("{} requires a value", iarg))),
+                }
+            } else if iarg.starts_with("-") {
+                logs_args.push(iarg.to_string());
+            } else if pod_query.is_none() {
+                pod_query = Some(iarg.to_string());
+            } else {
+                return Err(Error::msg(format!(
+                    "Only one pod-query is supported by {} log source, got also \"{}\"",
+                    BINARY_KUBECTL, iarg
+                )));
+            }
+        }
+
+        if namespace.is_some() {
+            all_namespaces = false;
+        }
+
+        match pod_query {
+            Some(pod_query) => Ok((namespace, all_namespaces, pod_query, logs_args)),
+            None => Err(Error::msg("pod-query is missing")),
+        }
+    }
+
+    fn get_pods(
+        &self,
+        context: &Context,
+        namespace: &Option<String>,
+        all_namespaces: bool,
+    ) -> Result<Vec<Value>> {
+        let mut get_pods_args: Vec<String> = vec![
+            "--context".into(),
+            context.name.to_string(),
+            "get".into(),
+            "pods".into(),
+            "--output".into(),
+            "json".into(),
+        ];
+
+        if let Some(namespace) = namespace {
+            get_pods_args.push("--namespace".into());
+            get_pods_args.push(namespace.to_string());
+        } else if all_namespaces {
+            get_pods_args.push("--all-namespaces".into());
+        }
+
+        let mut streamer = CommandStreamer::new(BINARY_KUBECTL, &get_pods_args, None)?;
+        // only stdout is JSON, stderr tells why it is not there
+        let (output, stderr_output) = streamer.get_all_lines(true, false)?;
+        let output = output.unwrap_or_default();
+
+        let parsed: Value = match serde_json::from_str(&output) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "cannot parse output of \"{}\" ({}): {}",
+                    streamer.format_command()?,
+                    err,
+                    match stderr_output {
+                        Some(stderr_output) if !stderr_output.trim().is_empty() => stderr_output,
+                        _ => output,
+                    }
+                    .trim()
+                )))
+            }
+        };
+
+        return match parsed["items"].as_array() {
+            Some(items) => Ok(items.clone()),
+            None => Ok(vec![]),
+        };
+    }
+
+    /// Returns containers of the context's pods which match the pod-query.
+    fn get_targets(&self, context: &Context) -> Result<Vec<KubectlLogTarget>> {
+        let (namespace, all_namespaces, pod_query, _) = self.parse_ext_args()?;
+        let pod_query_regex = Regex::new(&pod_query)?;
+        let mut targets = vec![];
+
+        for ipod in self.get_pods(context, &namespace, all_namespaces)? {
+            let pod_name = ipod["metadata"]["name"].as_str().unwrap_or_default();
+
+            if !pod_query_regex.is_match(pod_name) {
+                continue;
+            }
+
+            let containers = match ipod["spec"]["containers"].as_array() {
+                Some(containers) => containers.clone(),
+                None => continue,
+            };
+
+            for icontainer in containers {
+                targets.push(KubectlLogTarget {
+                    context_name: context.name.to_string(),
+                    node_name: ipod["spec"]["nodeName"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    namespace: ipod["metadata"]["namespace"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    pod_name: pod_name.to_string(),
+                    container_name: icontainer["name"].as_str().unwrap_or_default().to_string(),
+                });
+            }
+        }
+
+        return Ok(targets);
+    }
+
+    fn get_logs_args(
+        &self,
+        context: &Context,
+        target: &KubectlLogTarget,
+        logs_args: &[String],
+    ) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "--context".into(),
+            context.name.to_string(),
+            "logs".into(),
+            "--namespace".into(),
+            target.namespace.to_string(),
+            target.pod_name.to_string(),
+            "--container".into(),
+            target.container_name.to_string(),
+            "--timestamps".into(),
+            "--since".into(),
+            self.options.since.to_string(),
+        ];
+
+        if self.options.follow {
+            args.push("--follow".into());
+        }
+
+        args.extend_from_slice(logs_args);
+
+        return args;
+    }
+}
+
+impl LogSource for KubectlLogSource {
+    fn get_name(&self) -> &str {
+        return BINARY_KUBECTL;
+    }
+
+    fn get_required_binaries(&self) -> Vec<RequiredBinary> {
+        return vec![RequiredBinary {
+            name: BINARY_KUBECTL,
+            url: None,
+        }];
+    }
+
+    fn spawn(
+        &mut self,
+        context: &Context,
+        multi_streamer: &mut MultiCommandStreamer,
+    ) -> Result<()> {
+        let (_, _, _, logs_args) = self.parse_ext_args()?;
+
+        for target in self.get_targets(context)? {
+            let kubectl_logs_args = self.get_logs_args(context, &target, &logs_args);
+
+            multi_streamer.add(
+                BINARY_KUBECTL,
+                &kubectl_logs_args,
+                Some(context.name.to_string()),
+            )?;
+
+            // the command could be queued, not run yet
+            self.targets.insert(
+                CommandStreamer::format_program_args(BINARY_KUBECTL, &kubectl_logs_args),
+                target,
+            );
+        }
+
+        self.contexts.push(context.clone());
+
+        return Ok(());
+    }
+
+    fn spawn_new(&mut self, multi_streamer: &mut MultiCommandStreamer) -> Result<()> {
+        let (_, _, _, logs_args) = self.parse_ext_args()?;
+
+        for context in self.contexts.clone() {
+            for target in self.get_targets(&context)? {
+                if self
+                    .targets
+                    .values()
+                    .any(|itarget| itarget.is_same_container(&target))
+                {
+                    continue;
+                }
+
+                let kubectl_logs_args = self.get_logs_args(&context, &target, &logs_args);
+
+                // the context is streamed already, so the command is run right away, not queued
+                multi_streamer.add_streamer(CommandStreamer::new(
+                    BINARY_KUBECTL,
+                    &kubectl_logs_args,
+                    Some(context.name.to_string()),
+                )?);
+
+                self.targets.insert(
+                    CommandStreamer::format_program_args(BINARY_KUBECTL, &kubectl_logs_args),
+                    target,
+                );
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn parse(
+        &self,
+        streamer: &CommandStreamer,
+        lines: &str,
+        regex: &SternJSONRegEx,
+    ) -> Vec<SternJSON> {
+        let target = match streamer.format_command() {
+            Ok(command) => self.targets.get(&command),
+            Err(_) => None,
+        };
+
+        let target = match target {
+            Some(target) => target,
+            None => return SternJSON::parse(lines, Some(regex)),
+        };
+
+        let mut parsed = vec![];
+
+        for iline in tokenize_by(lines, "\n", -1, true, true) {
+            parsed.push(SternJSON::from_fields(
+                &iline,
+                // RFC3339 timestamp with nanoseconds, the message follows it
+                &iline,
+                &target.node_name,
+                &target.namespace,
+                &target.pod_name,
+                &target.container_name,
+                Some(regex),
+            ));
+        }
+
+        return parsed;
+    }
+
+    fn respawn(
+        &mut self,
+        streamer: &CommandStreamer,
+        since: &str,
+    ) -> Result<Option<CommandStreamer>> {
+        let command = streamer.format_command()?;
+
+        let target = match self.targets.get(&command) {
+            Some(target) => target,
+            None => return Ok(Some(respawn_since(streamer, since)?)),
+        };
+        let context = match self
+            .contexts
+            .iter()
+            .find(|icontext| icontext.name == target.context_name)
+        {
+            Some(context) => context,
+            None => return Ok(Some(respawn_since(streamer, since)?)),
+        };
+
+        // a deleted pod is not followed anymore, its replacement is picked up by spawn_new
+        let target = match self
+            .get_targets(context)?
+            .into_iter()
+            .find(|itarget| itarget.is_same_container(target))
+        {
+            Some(target) => target,
+            None => {
+                self.targets.remove(&command);
+                return Ok(None);
+            }
+        };
+
+        let respawned = respawn_since(streamer, since)?;
+
+        // parse finds the target by the command, which has changed
+        self.targets.remove(&command);
+        self.targets.insert(respawned.format_command()?, target);
+
+        return Ok(Some(respawned));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_source(ext_args: &[&str]) -> KubectlLogSource {
+        return KubectlLogSource::new(LogSourceOptions {
+            stern_defaults: false,
+            since: "1h".into(),
+            follow: false,
+            ext_args: ext_args.iter().map(|iarg| iarg.to_string()).collect(),
+        });
+    }
+
+    #[test]
+    fn test_parse_ext_args() {
+        let (namespace, all_namespaces, pod_query, logs_args) = new_source(&[
+            "-n",
+            "web",
+            "--tail",
+            "10",
+            "^nginx-",
+            "--previous",
+            "--limit-bytes=5",
+        ])
+        .parse_ext_args()
+        .unwrap();
+
+        assert_eq!(namespace, Some("web".to_string()));
+        assert!(!all_namespaces);
+        assert_eq!(pod_query, "^nginx-");
+        assert_eq!(
+            logs_args,
+            vec!["--tail", "10", "--previous", "--limit-bytes=5"]
+        );
+
+        let (namespace, all_namespaces, _, _) = new_source(&["-A", "x"]).parse_ext_args().unwrap();
+
+        assert_eq!(namespace, None);
+        assert!(all_namespaces);
+    }
+
+    #[test]
+    fn test_parse_ext_args_errors() {
+        assert!(new_source(&["x", "--tail"]).parse_ext_args().is_err());
+        assert!(new_source(&["x", "y"]).parse_ext_args().is_err());
+        assert!(new_source(&["--tail", "10"]).parse_ext_args().is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Error, Result};
+
+use super::{KubectlLogSource, SternLogSource};
+use crate::command_streamer::{CommandStreamer, MultiCommandStreamer};
+use crate::kubectl::Context;
+use crate::stern_json::SternJSON;
+use crate::stern_json_regex::SternJSONRegEx;
+
+/// A binary which must exist in PATH for a log source to work.
+pub struct RequiredBinary {
+    pub name: &'static str,
+    pub url: Option<&'static str>,
+}
+
+/// Options shared by every log source, taken from the command line.
+pub struct LogSourceOptions {
+    pub stern_defaults: bool,
+    pub since: String,
+    pub follow: bool,
+    pub ext_args: Vec<String>,
+}
+
+/// A backend which produces log lines for Kubernetes contexts.
+///
+/// The source spawns one or more commands per context into a `MultiCommandStreamer`,
+/// streams their output and parses it into `SternJSON` records, so everything
+/// after parsing (filtering, fixing up, printing) stays the same for every backend.
+pub trait LogSource {
+    fn get_name(&self) -> &str;
+
+    fn get_required_binaries(&self) -> Vec<RequiredBinary>;
+
+    fn spawn(&mut self, context: &Context, multi_streamer: &mut MultiCommandStreamer)
+        -> Result<()>;
+
+    fn stream<'a>(
+        &mut self,
+        multi_streamer: &'a mut MultiCommandStreamer,
+    ) -> Vec<(Result<Option<String>>, &'a CommandStreamer, bool)> {
+        return multi_streamer.get_lines(-1, true, true);
+    }
+
+    fn parse(
+        &self,
+        streamer: &CommandStreamer,
+        lines: &str,
+        regex: &SternJSONRegEx,
+    ) -> Vec<SternJSON>;
+
+    /// Runs the command of an exited streamer again, for logs newer than `since`,
+    /// returns None if there is nothing to stream anymore (e.g. its pod is gone).
+    fn respawn(
+        &mut self,
+        streamer: &CommandStreamer,
+        since: &str,
+    ) -> Result<Option<CommandStreamer>> {
+        return Ok(Some(respawn_since(streamer, since)?));
+    }
+
+    /// Runs commands for what appeared since `spawn` (e.g. new pods) while following,
+    /// stern picks new pods up by itself.
+    fn spawn_new(&mut self, _multi_streamer: &mut MultiCommandStreamer) -> Result<()> {
+        return Ok(());
+    }
+}
+
+/// Runs the command of a streamer again with its `--since` value replaced,
+/// or added if the command had none.
+pub fn respawn_since(streamer: &CommandStreamer, since: &str) -> Result<CommandStreamer> {
+    let program = match streamer.get_program() {
+        Some(program) => program,
+        None => return Err(Error::msg("program not set")),
+    };
+    let mut args = match streamer.get_args() {
+        Some(args) => args.clone(),
+        None => return Err(Error::msg("args not set")),
+    };
+    let mut has_since = false;
+
+    for iarg_index in 0..args.len() {
+        if args[iarg_index] == "--since" && iarg_index + 1 < args.len() {
+            args[iarg_index + 1] = since.to_string();
+            has_since = true;
+        } else if args[iarg_index].starts_with("--since=") {
+            args[iarg_index] = format!("--since={}", since);
+            has_since = true;
+        }
+    }
+
+    if !has_since {
+        args.push("--since".into());
+        args.push(since.to_string());
+    }
+
+    return CommandStreamer::new(program, &args, streamer.user_data.clone());
+}
+
+pub fn new_log_source(name: &str, options: LogSourceOptions) -> Result<Box<dyn LogSource>> {
+    return match name {
+        "stern" => Ok(Box::new(SternLogSource::new(options))),
+        "kubectl" => Ok(Box::new(KubectlLogSource::new(options))),
+        _ => Err(Error::msg(format!("Unknown log source \"{}\"", name))),
+    };
+}
@@ -0,0 +1,8 @@
+pub mod kubectl_log_source;
+#[allow(clippy::module_inception)]
+pub mod log_source;
+pub mod stern_log_source;
+
+pub use kubectl_log_source::KubectlLogSource;
+pub use log_source::{new_log_source, respawn_since, LogSource, LogSourceOptions, RequiredBinary};
+pub use stern_log_source::SternLogSource;
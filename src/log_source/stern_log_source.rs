@@ -0,0 +1,75 @@
+use anyhow::Result;
+
+use super::{LogSource, LogSourceOptions, RequiredBinary};
+use crate::command_streamer::{CommandStreamer, MultiCommandStreamer};
+use crate::consts::{BINARY_STERN, BINARY_STERN_URL};
+use crate::kubectl::Context;
+use crate::stern_json::SternJSON;
+use crate::stern_json_regex::SternJSONRegEx;
+
+/// Streams logs by running `stern --output json` for each context.
+pub struct SternLogSource {
+    options: LogSourceOptions,
+}
+
+impl SternLogSource {
+    pub fn new(options: LogSourceOptions) -> Self {
+        return SternLogSource { options };
+    }
+}
+
+impl LogSource for SternLogSource {
+    fn get_name(&self) -> &str {
+        return BINARY_STERN;
+    }
+
+    fn get_required_binaries(&self) -> Vec<RequiredBinary> {
+        return vec![RequiredBinary {
+            name: BINARY_STERN,
+            url: Some(BINARY_STERN_URL),
+        }];
+    }
+
+    fn spawn(
+        &mut self,
+        context: &Context,
+        multi_streamer: &mut MultiCommandStreamer,
+    ) -> Result<()> {
+        let mut stern_args: Vec<String> = vec![];
+
+        stern_args.push("--context".into());
+        stern_args.push(context.name.to_string());
+
+        if self.options.stern_defaults {
+            stern_args.append(&mut vec![
+                "--all-namespaces".into(),
+                "--output".into(),
+                "json".into(),
+                "--timestamps=short".into(),
+                "--since".into(),
+                self.options.since.to_string(),
+                "--timezone".into(),
+                "UTC".into(),
+            ]);
+
+            if !self.options.follow {
+                stern_args.push("--no-follow".into());
+            }
+        }
+
+        stern_args.append(&mut self.options.ext_args.clone());
+
+        multi_streamer.add(BINARY_STERN, &stern_args, Some(context.name.to_string()))?;
+
+        return Ok(());
+    }
+
+    fn parse(
+        &self,
+        _streamer: &CommandStreamer,
+        lines: &str,
+        regex: &SternJSONRegEx,
+    ) -> Vec<SternJSON> {
+        return SternJSON::parse(lines, Some(regex));
+    }
+}
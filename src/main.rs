@@ -3,42 +3,79 @@ extern crate jsonxf;
 pub mod command_streamer;
 
 mod arg_parser;
+mod config;
 mod consts;
+mod entry_grouper;
+mod entry_pipeline;
 mod env_utils;
+mod exception_summary;
+mod field_predicate;
 mod file_utils;
 mod json_utils;
 mod kubectl;
+mod log_level;
+mod log_merger;
+mod log_source;
 mod message_regex;
+mod multi_line_joiner;
+mod option_source;
+mod output_format;
+mod print_options;
+mod recognizer;
 mod stats;
 mod stern_json;
 mod stern_json_regex;
+mod stream_supervisor;
 mod string_utils;
+mod template;
 
 use crate::arg_parser::ArgParser;
-use crate::command_streamer::{CommandStreamer, MultiCommandStreamer};
+use crate::command_streamer::MultiCommandStreamer;
+use crate::config::Config;
+use crate::entry_grouper::{EntryGroup, EntryGrouper};
+use crate::entry_pipeline::EntryPipeline;
 use crate::env_utils::{args_to_string, args_vec};
-use crate::file_utils::my_println;
+use crate::exception_summary::ExceptionSummary;
+use crate::field_predicate::FieldPredicate;
+use crate::file_utils::{
+    my_println, my_println_reserved, open_buffered_reader, set_stdout_reserved,
+};
+use crate::json_utils::{get_value_by_path, value_to_string};
+use crate::kubectl::context_pattern::{select_contexts, ContextPattern};
 use crate::kubectl::Context;
+use crate::log_level::LogLevel;
+use crate::log_merger::LogMerger;
+use crate::log_source::{new_log_source, LogSource, LogSourceOptions};
 use crate::message_regex::MessageRegEx;
-use crate::stats::Stats;
+use crate::multi_line_joiner::MultiLineJoiner;
+use crate::option_source::OptionSource;
+use crate::output_format::{entry_to_json_line, OutputFormat};
+use crate::print_options::PrintOptions;
+use crate::recognizer::{Recognizer, RecognizerRegistry, Renderer};
+use crate::stats::{Stats, StatsFormat};
 use crate::string_utils::{
     current_datetime_string, normalize_spaces, replace_by_regex, replace_non_alphabetic_with_space,
-    tokenize_by,
+    string_to_duration, tokenize_by,
 };
+use crate::template::{EntryLine, Template, DEFAULT_TEMPLATE};
 use anyhow::{Error, Result};
-use consts::{APP_NAME, APP_VERSION, BINARY_KUBECTL, BINARY_STERN, BINARY_STERN_URL};
+use chrono::{DateTime, Utc};
+use consts::{APP_NAME, APP_VERSION, BINARY_KUBECTL};
 use execution_time::ExecutionTime;
 use kubectl::Kubectl;
 use realpath::realpath;
+use regex::Regex;
 use serde_json::Value;
 use std::collections::{BTreeMap, HashMap};
 use std::env::set_current_dir;
 use std::fs::{canonicalize, File, OpenOptions};
+use std::io::BufRead;
 use std::ops::Not;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use stern_json::SternJSON;
 use stern_json_regex::SternJSONRegEx;
+use stream_supervisor::StreamSupervisor;
 use which::which;
 
 fn get_full_app_name() -> String {
@@ -52,25 +89,37 @@ fn print_app_name(log_handle: &mut Option<File>) -> Result<()> {
     return Ok(());
 }
 
-fn check_required_binaries() -> Result<()> {
-    match which(BINARY_KUBECTL) {
-        Err(err) => {
-            return Err(Error::msg(format!(
-                "Make sure \"{}\" exists in your PATH ({})",
-                BINARY_KUBECTL, err
-            )))
+fn check_required_binaries(log_source: &dyn LogSource, kubectl_required: bool) -> Result<()> {
+    if kubectl_required {
+        match which(BINARY_KUBECTL) {
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "Make sure \"{}\" exists in your PATH ({})",
+                    BINARY_KUBECTL, err
+                )))
+            }
+            _ => {}
         }
-        _ => {}
     }
 
-    match which(BINARY_STERN) {
-        Err(err) => {
-            return Err(Error::msg(format!(
-                "Make sure \"{}\" exists in your PATH, get it from {} ({})",
-                BINARY_STERN_URL, BINARY_STERN, err
-            )))
+    for binary in log_source.get_required_binaries() {
+        match which(binary.name) {
+            Err(err) => match binary.url {
+                Some(url) => {
+                    return Err(Error::msg(format!(
+                        "Make sure \"{}\" exists in your PATH, get it from {} ({})",
+                        binary.name, url, err
+                    )))
+                }
+                None => {
+                    return Err(Error::msg(format!(
+                        "Make sure \"{}\" exists in your PATH ({})",
+                        binary.name, err
+                    )))
+                }
+            },
+            _ => {}
         }
-        _ => {}
     }
 
     return Ok(());
@@ -94,14 +143,17 @@ fn get_app_exe_name() -> String {
         .to_string();
 }
 
-fn print_usages() {
+fn print_usages(args: &ArgParser, kv_args_sources: &BTreeMap<String, OptionSource>) {
     println!("Usage:");
     println!("\t{} [option] -- <pod-query>", get_app_exe_name());
     println!("");
     println!("Options:");
     println!("\t-h, --help                                  this help message");
-    println!("\t-c, --context <string>[,...]                select context(s) separated by comma or use \"all\" for all contexts (default \"default\")");
+    println!("\t-c, --context <string>[,...]                select context(s) separated by comma or use \"all\" for all contexts (default \"default\"); a context can be a glob like \"prod-*\" or a regex between slashes like \"/^stg-(eu|us)/\"");
+    println!("\t    --exclude-context <string>[,...]        skip context(s) selected by --context, names, globs or regexes like in --context, can be repeated");
+    println!("\t    --profile <string>                      use option values of such profile of the config file, see \"Config File\" in README");
     println!("\t-a, --all-at-once <bool>                    gather logs from all contexts at once (default \"false\"); use with caution since it could be heavy to your network infrastructure");
+    println!("\t    --parallel <number>                     gather logs from at most such number of contexts at once, the next context starts as soon as one finishes, with --follow it must not be less than the number of contexts");
     println!("\t-s, --skip-invalid-messages <bool>          skip invalid messages (default \"false\"); skip non-json messages returned by Stern");
     println!("\t-b, --blank-line-after-entry <bool>         blank line after each log entry (default \"false\")");
     println!("\t-i, --include-container <string>[,...]      include logs from only such container(s); use \"all\" for all containers (default \"all\")");
@@ -112,80 +164,480 @@ fn print_usages() {
     println!("\t-t, --since <duration>                      return logs newer than a relative duration like 5s, 2m, or 3h (default \"1h\")");
     println!("\t-r, --space-after-message <bool>            add a space character after each message (default \"true\")");
     println!("\t-g, --follow                                wait for new messages");
+    println!("\t    --restart <bool>                        with --follow restart exited streams with exponential backoff, from the last seen entry (default \"true\")");
+    println!("\t    --lossy-utf8 <bool>                     replace invalid UTF-8 in the output of the commands by U+FFFD, counted in stats, instead of reporting an error (default \"true\")");
+    println!("\t-o, --output <string>                       output format, \"text\" or \"jsonl\" for one JSON object per entry (default \"text\")");
+    println!("\t    --template <string>                     output line template like \"{{ts}} [{{level}}] {{pod}}/{{container}}: {{message}}\", any SternJSON field or dotted path of the JSON message can be used (default \"{}\")", DEFAULT_TEMPLATE);
+    println!("\t    --recognizers <filename>                JSON file with recognizers of JSON message shapes, checked before the built-in ones");
+    println!("\t    --min-level <level>                     print only entries of such level or more severe: trace, debug, info, warn, error or fatal; entries without a known level are always printed");
+    println!("\t    --grep <regex>                          print only entries which message matches the regex, can be repeated to match any of them");
+    println!("\t    --grep-v <regex>                        skip entries which message matches the regex, can be repeated");
+    println!("\t    --grep-raw <bool>                       match --grep and --grep-v also against the raw line (default \"false\")");
+    println!("\t    --where <field><operator><value>        print only entries which JSON message field matches, like \"response_code>=500\" or \"path~^/api\"; operators are = != >= <= > < and ~ (regex), can be repeated to match all of them");
+    println!("\t    --trace <request_id>                    print only entries of such request, from all contexts, pods and containers as one timeline");
+    println!("\t    --group-by <field>                      print entries grouped by a field of the JSON message, like \"request_id\", each group as one timeline");
+    println!("\t    --group-window <duration>               how long a group of --trace or --group-by waits for new entries with --follow before it is printed (default \"2s\")");
+    println!("\t    --join-multi-line <bool>                join Java, Python and Go stack trace lines into one entry per container (default \"false\")");
+    println!("\t    --multi-line-timeout <duration>         how long the last entry of a container waits for more stack trace lines with --follow (default \"500ms\")");
+    println!("\t    --exception-summary <bool>              print exceptions grouped by type and top stack frames at the end (default \"true\")");
+    println!("\t    --stats-format <string>                 statistics printed at the end, \"text\", \"table\" for a breakdown by context, namespace, pod and container or \"json\" (the only output to stdout, other messages go to stderr) (default \"text\")");
+    println!("\t-j, --merge-by-time <bool>                  print logs of all contexts, pods and containers in timestamp order (default \"false\")");
+    println!("\t    --merge-window <duration>               how long entries are held for reordering with --merge-by-time and --follow (default \"2s\")");
+    println!("\t-e, --from-file <path>[,...]                replay logs from previously saved \"stern --output json\" file(s) instead of streaming them, use \"-\" for stdin");
+    println!("\t-k, --file-context <string>                 context name used for --from-file entries (default is the file name)");
+    println!("\t-l, --log-source <string>                   backend used to stream logs, \"stern\" or \"kubectl\" (default \"stern\")");
     println!(
         "\t-q, --quiet                                 do not output any log messages to stdout"
     );
     println!("");
+    println!("Each option with a value falls back to a DOH_* environment variable, like DOH_SINCE for --since");
+    println!("or DOH_INCLUDE_CONTAINER for --include-container, then to the config file and then to the default.");
+    println!();
+    println!("Effective values:");
+
+    for iarg in args.kv_args.keys() {
+        let source = match kv_args_sources.get(iarg) {
+            Some(source) => source.to_string(),
+            None => OptionSource::CommandLine.to_string(),
+        };
+
+        // every value of a repeatable option comes from the same source
+        for iarg_value in args.get_kv_arg_values(iarg, false) {
+            println!("\t{:<42}  {:<20}  ({})", iarg, iarg_value, source);
+        }
+    }
+
+    println!();
     println!("Example:");
     println!("\t{} -- nginx", get_app_exe_name());
+    println!("\t{} -e production.json", get_app_exe_name());
     println!("");
 }
 
-fn run(args: ArgParser, log_handle: &mut Option<File>) -> Result<()> {
-    let mut contexts: Vec<Context> = vec![];
-    let arg_context = args.get_kv_arg_string("--context", false, false).unwrap();
+fn run(args: ArgParser, config: &Config, log_handle: &mut Option<File>) -> Result<()> {
     let mut stats = Stats::new();
+    let arg_exception_summary = args.get_bool_kv_arg("--exception-summary", false).unwrap();
+    let stats_format = StatsFormat::parse(
+        &args
+            .get_kv_arg_string("--stats-format", false, false)
+            .unwrap(),
+    )?;
+    let output_format =
+        OutputFormat::parse(&args.get_kv_arg_string("--output", false, false).unwrap())?;
 
-    if arg_context == "all" {
-        contexts = Kubectl::get_contexts(log_handle)?;
+    if args.kv_args.contains_key("--from-file") {
+        run_from_files(&args, &mut stats, log_handle)?;
     } else {
-        for icontext in tokenize_by(&arg_context, ",".into(), -1, true, true) {
-            contexts.push(Context {
-                auth_info: "".to_string(),
-                current: false,
-                name: icontext.to_string(),
-                cluster: "".to_string(),
-                namespace: "".to_string(),
-            });
+        run_from_contexts(args, config, &mut stats, log_handle)?;
+    }
+
+    print_stats(&stats, &stats_format, &output_format, arg_exception_summary)?;
+
+    let failed_contexts = stats.get_failed_contexts();
+
+    if !failed_contexts.is_empty() {
+        return Err(Error::msg(format!(
+            "streams failed in context(s): {}",
+            failed_contexts.join(", ")
+        )));
+    }
+
+    return Ok(());
+}
+
+fn print_stats(
+    stats: &Stats,
+    stats_format: &StatsFormat,
+    output_format: &OutputFormat,
+    arg_exception_summary: bool,
+) -> Result<()> {
+    if *stats_format == StatsFormat::Json {
+        // stdout has only this object, unless it has JSON Lines entries already
+        if *output_format == OutputFormat::JsonLines {
+            return my_println(&mut None, &false, &true, &stats.to_json().to_string());
+        }
+
+        return my_println_reserved(&mut None, &false, &true, &stats.to_json().to_string());
+    }
+
+    my_println(
+        &mut None,
+        &false,
+        &true,
+        &format!("Total logs: {}", stats.total_logs),
+    )?;
+    my_println(
+        &mut None,
+        &false,
+        &true,
+        &format!("Filtered out logs: {}", stats.filtered_out_logs),
+    )?;
+    my_println(
+        &mut None,
+        &false,
+        &true,
+        &format!("Printed logs: {}", stats.printed_logs),
+    )?;
+    my_println(
+        &mut None,
+        &false,
+        &true,
+        &format!("Invalid logs: {}", stats.invalid_logs),
+    )?;
+    my_println(
+        &mut None,
+        &false,
+        &true,
+        &format!(
+            "Processed: {} bytes, {:.1} lines/s",
+            stats.bytes,
+            stats.get_lines_per_second()
+        ),
+    )?;
+
+    if stats.replaced_chars > 0 {
+        my_println(
+            &mut None,
+            &false,
+            &true,
+            &format!("Replaced invalid UTF-8 sequences: {}", stats.replaced_chars),
+        )?;
+    }
+
+    if !stats.level_logs.is_empty() {
+        my_println(
+            &mut None,
+            &false,
+            &true,
+            &format!(
+                "Logs by level: {}",
+                Stats::format_levels(&stats.level_logs, ": ", ", ")
+            ),
+        )?;
+    }
+
+    if *stats_format == StatsFormat::Table {
+        for iline in stats.to_table() {
+            my_println(&mut None, &false, &true, &format!("\t{}", iline))?;
+        }
+    }
+
+    if arg_exception_summary {
+        print_exception_summary(&stats.exceptions)?;
+    }
+
+    print_stream_failures(stats)?;
+
+    return Ok(());
+}
+
+fn print_stream_failures(stats: &Stats) -> Result<()> {
+    // contexts whose streams only wrote to stderr, but did not fail, are listed as warnings
+    for (title, failed) in [("Stream failures:", true), ("Stream warnings:", false)] {
+        let mut contexts = stats
+            .failures
+            .iter()
+            .filter(|(_, failures)| (failures.failed_streams > 0) == failed)
+            .peekable();
+
+        if contexts.peek().is_none() {
+            continue;
+        }
+
+        my_println(&mut None, &false, &true, &title.into())?;
+
+        for (context, failures) in contexts {
+            let exit_statuses = if failures.exit_statuses.is_empty() {
+                "".to_string()
+            } else {
+                format!(" ({})", failures.exit_statuses.join(", "))
+            };
+
+            if failed {
+                my_println(
+                    &mut None,
+                    &false,
+                    &true,
+                    &format!(
+                        "\t{}: {} failed stream(s){}",
+                        context, failures.failed_streams, exit_statuses
+                    ),
+                )?;
+            } else {
+                my_println(&mut None, &false, &true, &format!("\t{}:", context))?;
+            }
+
+            for iline in failures.stderr_lines.iter() {
+                my_println(&mut None, &false, &true, &format!("\t\tstderr: {}", iline))?;
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+fn print_exception_summary(exceptions: &ExceptionSummary) -> Result<()> {
+    let groups = exceptions.get_sorted_groups();
+
+    if groups.is_empty() {
+        return Ok(());
+    }
+
+    let format_datetime = |datetime: Option<DateTime<Utc>>| match datetime {
+        Some(datetime) => datetime.format("%m-%d %H:%M:%S").to_string(),
+        None => "-".to_string(),
+    };
+
+    my_println(&mut None, &false, &true, &"Exceptions:".into())?;
+    my_println(
+        &mut None,
+        &false,
+        &true,
+        &format!(
+            "\t{:>7}  {:<14}  {:<14}  {:<20}  {}",
+            "COUNT", "FIRST SEEN", "LAST SEEN", "CONTEXTS", "EXCEPTION"
+        ),
+    )?;
+
+    for igroup in groups {
+        let mut sample_message: String = igroup.sample_message.chars().take(80).collect();
+
+        // like the final line of a Python traceback, which starts with the type already
+        if !igroup.sample_message.starts_with(&igroup.exception_type) {
+            sample_message = format!("{}: {}", igroup.exception_type, sample_message);
+        }
+
+        my_println(
+            &mut None,
+            &false,
+            &true,
+            &format!(
+                "\t{:>7}  {:<14}  {:<14}  {:<20}  {}",
+                igroup.count,
+                format_datetime(igroup.first_seen),
+                format_datetime(igroup.last_seen),
+                igroup.contexts.join(","),
+                sample_message
+            ),
+        )?;
+    }
+
+    return Ok(());
+}
+
+fn run_from_contexts(
+    args: ArgParser,
+    config: &Config,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    let arg_context = args.get_kv_arg_string("--context", false, false).unwrap();
+    let mut log_source = create_log_source(&args)?;
+
+    let include_patterns = ContextPattern::parse_list(
+        &config.expand_context_groups(&tokenize_by(&arg_context, ",", -1, true, true)),
+    )?;
+    let mut exclude_patterns = vec![];
+
+    for iexclude in args.get_kv_arg_values("--exclude-context", false) {
+        exclude_patterns.extend(ContextPattern::parse_list(
+            &config.expand_context_groups(&tokenize_by(&iexclude, ",", -1, true, true)),
+        )?);
+    }
+
+    // kubectl reads the contexts only when there is no kubeconfig file
+    check_required_binaries(log_source.as_ref(), !Kubectl::has_kubeconfig())?;
+
+    let mut contexts = select_contexts(
+        &Kubectl::get_contexts(log_handle)?,
+        &include_patterns,
+        &exclude_patterns,
+    )?;
+
+    run_level_0(args, log_source.as_mut(), &mut contexts, stats, log_handle)?;
+
+    return Ok(());
+}
+
+fn get_file_context(pathname: &str) -> String {
+    if pathname == "-" {
+        return "stdin".to_string();
+    }
+
+    return match Path::new(pathname).file_stem() {
+        Some(file_stem) => file_stem.to_string_lossy().to_string(),
+        None => pathname.to_string(),
+    };
+}
+
+fn run_from_files(
+    args: &ArgParser,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    let regex = SternJSONRegEx::new();
+    let message_regex = MessageRegEx::new();
+    let print_options = PrintOptions::new(args)?;
+    let mut pipeline = EntryPipeline {
+        print_options: &print_options,
+        message_regex: &message_regex,
+        joiner: create_multi_line_joiner(args, false)?,
+        merger: create_log_merger(args, false)?,
+        grouper: create_entry_grouper(args, false)?,
+    };
+
+    let arg_from_file = args
+        .get_kv_arg_array_string("--from-file", ",", false, false)
+        .unwrap();
+    let arg_file_context = args.get_kv_arg_string("--file-context", false, false);
+
+    my_println(
+        log_handle,
+        &true,
+        &true,
+        &"Replaying logs from files:".into(),
+    )?;
+
+    for ipathname in arg_from_file.iter() {
+        my_println(log_handle, &true, &true, &format!("\t{}", ipathname))?;
+    }
+
+    for ipathname in arg_from_file.iter() {
+        let context = match &arg_file_context {
+            Some(arg_file_context) => arg_file_context.to_string(),
+            None => get_file_context(ipathname),
+        };
+        let mut reader = open_buffered_reader(ipathname)?;
+        let mut line = vec![];
+
+        loop {
+            line.clear();
+
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+
+            let mut parsed_lines = SternJSON::parse(&String::from_utf8_lossy(&line), Some(&regex));
+
+            if let Some(joiner) = &mut pipeline.joiner {
+                parsed_lines = joiner.push(&context, parsed_lines);
+            }
+
+            dispatch_parsed_stern_json(&context, parsed_lines, &mut pipeline, stats, log_handle)?;
         }
     }
 
-    if !contexts.is_empty() {
-        run_level_0(args, &mut contexts, &mut stats, log_handle)?;
+    if let Some(joiner) = &mut pipeline.joiner {
+        dispatch_joined_stern_json(joiner.pop_all(), &mut pipeline, stats, log_handle)?;
+    }
+
+    if let Some(merger) = &mut pipeline.merger {
+        print_merged_stern_json(merger.pop_all(), &mut pipeline, stats, log_handle)?;
     }
 
-    println!("Total logs: {}", stats.total_logs);
-    println!("Filtered out logs: {}", stats.filtered_out_logs);
-    println!("Printed logs: {}", stats.printed_logs);
+    if let Some(grouper) = &mut pipeline.grouper {
+        print_entry_groups(
+            grouper.pop_all(),
+            &grouper.field,
+            &print_options,
+            &message_regex,
+            stats,
+            log_handle,
+        )?;
+    }
 
     return Ok(());
 }
 
+fn create_log_source(args: &ArgParser) -> Result<Box<dyn LogSource>> {
+    let arg_log_source = args
+        .get_kv_arg_string("--log-source", false, false)
+        .unwrap();
+
+    return new_log_source(
+        &arg_log_source,
+        LogSourceOptions {
+            stern_defaults: args.get_bool_kv_arg("--stern-defaults", false).unwrap(),
+            since: args.get_kv_arg_string("--since", false, false).unwrap(),
+            follow: args.args.contains(&"--follow".into()),
+            ext_args: args.ext_args_as_str_vec(),
+        },
+    );
+}
+
+fn create_multi_line_joiner(args: &ArgParser, follow: bool) -> Result<Option<MultiLineJoiner>> {
+    if !args.get_bool_kv_arg("--join-multi-line", false).unwrap() {
+        return Ok(None);
+    }
+
+    if !follow {
+        return Ok(Some(MultiLineJoiner::new(None)));
+    }
+
+    let arg_multi_line_timeout = args
+        .get_kv_arg_string("--multi-line-timeout", false, false)
+        .unwrap();
+
+    return Ok(Some(MultiLineJoiner::new(Some(string_to_duration(
+        &arg_multi_line_timeout,
+    )?))));
+}
+
+fn create_log_merger(args: &ArgParser, follow: bool) -> Result<Option<LogMerger>> {
+    if !args.get_bool_kv_arg("--merge-by-time", false).unwrap() {
+        return Ok(None);
+    }
+
+    if !follow {
+        return Ok(Some(LogMerger::new(None)));
+    }
+
+    let arg_merge_window = args
+        .get_kv_arg_string("--merge-window", false, false)
+        .unwrap();
+
+    return Ok(Some(LogMerger::new(Some(string_to_duration(
+        &arg_merge_window,
+    )?))));
+}
+
+fn create_entry_grouper(args: &ArgParser, follow: bool) -> Result<Option<EntryGrouper>> {
+    let arg_trace = args.get_kv_arg_string("--trace", false, false);
+    let arg_group_by = args.get_kv_arg_string("--group-by", false, false);
+
+    let field = match (&arg_trace, &arg_group_by) {
+        (_, Some(arg_group_by)) => arg_group_by.to_string(),
+        (Some(_), None) => "request_id".to_string(),
+        (None, None) => return Ok(None),
+    };
+
+    let mut window = None;
+
+    if follow {
+        window = Some(string_to_duration(
+            &args
+                .get_kv_arg_string("--group-window", false, false)
+                .unwrap(),
+        )?);
+    }
+
+    return Ok(Some(EntryGrouper::new(&field, arg_trace, window)));
+}
+
 fn create_multi_streamer(
+    log_source: &mut dyn LogSource,
     contexts: &Vec<Context>,
-    arg_stern_defaults: bool,
-    arg_since: &String,
-    arg_ext_args: &Vec<String>,
-    arg_follow: &bool,
+    max_running_contexts: Option<usize>,
+    lossy_utf8: bool,
 ) -> Result<MultiCommandStreamer> {
     let mut multi_streamer = MultiCommandStreamer::new_empty();
 
-    for icontext in contexts {
-        let mut stern_args: Vec<String> = vec![];
-
-        stern_args.push("--context".into());
-        stern_args.push(icontext.name.to_string());
-
-        if arg_stern_defaults {
-            stern_args.append(&mut vec![
-                "--all-namespaces".into(),
-                "--output".into(),
-                "json".into(),
-                "--timestamps=short".into(),
-                "--since".into(),
-                arg_since.to_string(),
-                "--timezone".into(),
-                "UTC".into(),
-            ]);
-
-            if !*arg_follow {
-                stern_args.push("--no-follow".into());
-            }
-        }
+    multi_streamer
+        .set_max_running_groups(max_running_contexts)
+        .set_lossy_utf8(lossy_utf8);
 
-        stern_args.append(&mut arg_ext_args.clone());
-
-        multi_streamer.add(BINARY_STERN, &stern_args, Some(icontext.name.to_string()))?;
+    for icontext in contexts {
+        log_source.spawn(icontext, &mut multi_streamer)?;
     }
 
     return Ok(multi_streamer);
@@ -272,6 +724,7 @@ fn sync_log_file_handle(log_handle: &Option<File>) -> Result<()> {
 
 fn run_level_0(
     args: ArgParser,
+    log_source: &mut dyn LogSource,
     contexts: &mut Vec<Context>,
     stats: &mut Stats,
     log_handle: &mut Option<File>,
@@ -279,13 +732,41 @@ fn run_level_0(
     let regex = SternJSONRegEx::new();
     let message_regex = MessageRegEx::new();
 
-    let arg_stern_defaults = args.get_bool_kv_arg("--stern-defaults", false).unwrap();
     let arg_all_contexts_at_once = args.get_bool_kv_arg("--all-at-once", false).unwrap();
-    let arg_ext_args = args.ext_args_as_str_vec();
-    let arg_quiet = args.args.contains(&"--quiet".into());
-    let arg_follow = args.args.contains(&"--follow".into());
-    let arg_since: String = args.get_kv_arg_string("--since", false, false).unwrap();
+    // exited streams are restarted only when following
+    let arg_restart =
+        args.args.contains(&"--follow".into()) && args.get_bool_kv_arg("--restart", false).unwrap();
+    let arg_parallel = args
+        .get_kv_arg_string("--parallel", false, false)
+        .map(|arg_parallel| arg_parallel.parse::<usize>().unwrap());
+    let arg_lossy_utf8 = args.get_bool_kv_arg("--lossy-utf8", false).unwrap();
+
+    if let Some(arg_parallel) = arg_parallel {
+        // followed streams never end, so their slots would never be freed
+        if args.args.contains(&"--follow".into()) && arg_parallel < contexts.len() {
+            return Err(Error::msg(format!(
+                "--parallel {} is less than the number of contexts ({}), with --follow the rest of them would never start",
+                arg_parallel,
+                contexts.len()
+            )));
+        }
+    }
+
+    let print_options = PrintOptions::new(&args)?;
+    let mut pipeline = EntryPipeline {
+        print_options: &print_options,
+        message_regex: &message_regex,
+        joiner: create_multi_line_joiner(&args, args.args.contains(&"--follow".into()))?,
+        merger: create_log_merger(&args, args.args.contains(&"--follow".into()))?,
+        grouper: create_entry_grouper(&args, args.args.contains(&"--follow".into()))?,
+    };
 
+    my_println(
+        log_handle,
+        &true,
+        &true,
+        &format!("Log source: {}", log_source.get_name()),
+    )?;
     my_println(
         log_handle,
         &true,
@@ -294,24 +775,34 @@ fn run_level_0(
     )?;
 
     for icontext in contexts.iter() {
-        my_println(log_handle, &true, &true, &format!("\t{}", icontext.name))?;
+        my_println(
+            log_handle,
+            &true,
+            &true,
+            &format!(
+                "\t{} (cluster: {}, namespace: {})",
+                icontext.name,
+                icontext.cluster,
+                if icontext.namespace.is_empty() {
+                    "default"
+                } else {
+                    &icontext.namespace
+                }
+            ),
+        )?;
     }
 
-    if arg_all_contexts_at_once {
-        let mut multi_streamer = create_multi_streamer(
-            contexts,
-            arg_stern_defaults,
-            &arg_since,
-            &arg_ext_args,
-            &arg_follow,
-        )?;
+    if arg_all_contexts_at_once || arg_parallel.is_some() {
+        // with --parallel the next context starts as soon as one finishes
+        let mut multi_streamer =
+            create_multi_streamer(log_source, contexts, arg_parallel, arg_lossy_utf8)?;
 
         gather_logs_from_multi_streamer(
-            &args,
+            log_source,
             &mut multi_streamer,
             &regex,
-            &message_regex,
-            &arg_quiet,
+            &mut pipeline,
+            arg_restart,
             stats,
             log_handle,
         )?;
@@ -322,53 +813,157 @@ fn run_level_0(
             }
 
             let icontext = contexts.remove(0);
-            let mut multi_streamer = create_multi_streamer(
-                &vec![icontext],
-                arg_stern_defaults,
-                &arg_since,
-                &arg_ext_args,
-                &arg_follow,
-            )?;
+            let mut multi_streamer =
+                create_multi_streamer(log_source, &vec![icontext], None, arg_lossy_utf8)?;
 
             gather_logs_from_multi_streamer(
-                &args,
+                log_source,
                 &mut multi_streamer,
                 &regex,
-                &message_regex,
-                &arg_quiet,
+                &mut pipeline,
+                arg_restart,
                 stats,
                 log_handle,
             )?;
         }
     }
 
+    if let Some(grouper) = &mut pipeline.grouper {
+        print_entry_groups(
+            grouper.pop_all(),
+            &grouper.field,
+            &print_options,
+            &message_regex,
+            stats,
+            log_handle,
+        )?;
+    }
+
+    return Ok(());
+}
+
+/// Reports streamers which exited and runs them again once their backoff has passed,
+/// from time to time also runs new ones, e.g. for new pods.
+fn restart_exited_streamers(
+    log_source: &mut dyn LogSource,
+    multi_streamer: &mut MultiCommandStreamer,
+    supervisor: &mut StreamSupervisor,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    for (index, streamer) in multi_streamer.get_streamers().iter_mut().enumerate() {
+        let context = streamer.user_data.clone().unwrap_or_default();
+
+        if let Some((exit_status, backoff)) = supervisor.check_exited(index, streamer) {
+            stats.count_exit(&context, &exit_status);
+
+            my_println(
+                log_handle,
+                &true,
+                &true,
+                &format!(
+                    "Stream of context \"{}\" exited ({}), restarting in {:?}: {}",
+                    context,
+                    exit_status,
+                    backoff,
+                    streamer.format_command().unwrap()
+                ),
+            )?;
+        }
+
+        let since = match supervisor.pop_restart_since(index) {
+            Some(since) => since,
+            None => continue,
+        };
+
+        match log_source.respawn(streamer, &since) {
+            Ok(None) => {
+                supervisor.finish(index);
+
+                my_println(
+                    log_handle,
+                    &true,
+                    &true,
+                    &format!(
+                        "Stream of context \"{}\" has nothing to follow anymore: {}",
+                        context,
+                        streamer.format_command().unwrap()
+                    ),
+                )?;
+            }
+            Ok(Some(mut respawned)) => {
+                respawned.set_lossy_utf8(streamer.is_lossy_utf8());
+                *streamer = respawned;
+
+                my_println(
+                    log_handle,
+                    &true,
+                    &true,
+                    &format!("Restarted: {}", streamer.format_command().unwrap()),
+                )?;
+            }
+            Err(err) => {
+                let backoff = supervisor.schedule_restart(index);
+
+                my_println(
+                    log_handle,
+                    &true,
+                    &true,
+                    &format!(
+                        "Cannot restart stream of context \"{}\" ({}), retrying in {:?}",
+                        context, err, backoff
+                    ),
+                )?;
+            }
+        }
+    }
+
+    if !supervisor.pop_spawn_new() {
+        return Ok(());
+    }
+
+    let count_streamers = multi_streamer.get_streamers().len();
+
+    if let Err(err) = log_source.spawn_new(multi_streamer) {
+        my_println(
+            log_handle,
+            &true,
+            &true,
+            &format!("Cannot look for new streams ({})", err),
+        )?;
+    }
+
+    for streamer in &multi_streamer.get_streamers()[count_streamers..] {
+        my_println(
+            log_handle,
+            &true,
+            &true,
+            &format!("Running: {}", streamer.format_command().unwrap()),
+        )?;
+    }
+
     return Ok(());
 }
 
 fn gather_logs_from_multi_streamer(
-    args: &ArgParser,
+    log_source: &mut dyn LogSource,
     multi_streamer: &mut MultiCommandStreamer,
     regex: &SternJSONRegEx,
-    message_regex: &MessageRegEx,
-    arg_quiet: &bool,
+    pipeline: &mut EntryPipeline,
+    restart: bool,
     stats: &mut Stats,
     log_handle: &mut Option<File>,
 ) -> Result<()> {
-    let arg_skip_invalid_messages = args
-        .get_bool_kv_arg("--skip-invalid-messages", false)
-        .unwrap();
-    let arg_blank_line_after_entry = args
-        .get_bool_kv_arg("--blank-line-after-entry", false)
-        .unwrap();
-    let arg_space_after_message = args
-        .get_bool_kv_arg("--space-after-message", false)
-        .unwrap();
-    let arg_include_container =
-        args.get_kv_arg_array_string("--include-container", ",", false, false);
-    let arg_fix_up_messages = args.get_bool_kv_arg("--fix-up-messages", false).unwrap();
-    let arg_pretty_print_objects = args
-        .get_bool_kv_arg("--pretty-print-objects", false)
-        .unwrap();
+    let mut supervisor = if restart {
+        Some(StreamSupervisor::new())
+    } else {
+        None
+    };
+
+    // a supervised stream keeps its context's --parallel slot while waiting for a restart
+    multi_streamer.set_restarts_exited(restart);
+
+    multi_streamer.start_queued()?;
 
     for streamer in multi_streamer.get_streamers() {
         my_println(
@@ -383,26 +978,53 @@ fn gather_logs_from_multi_streamer(
         result?;
     }
 
-    while !multi_streamer.is_eof() || multi_streamer.has_data_in_buffers() {
-        let lines = multi_streamer.get_lines(-1, true, true);
+    // a supervised stream is never done, it is restarted once it exits
+    while supervisor.is_some() || !multi_streamer.is_eof() || multi_streamer.has_data_in_buffers() {
+        for streamer in multi_streamer.start_queued()? {
+            my_println(
+                log_handle,
+                &true,
+                &true,
+                &format!("Running: {}", streamer.format_command().unwrap()),
+            )?;
+        }
 
-        for (ilines, streamer, _) in lines {
+        let lines = log_source.stream(multi_streamer);
+
+        for (index, (ilines, streamer, is_stdout)) in lines.into_iter().enumerate() {
             match ilines {
                 Ok(ilines) => match ilines {
+                    Some(ilines) if !is_stdout => {
+                        // errors of the command itself, not log entries
+                        let context = streamer.user_data.as_ref().unwrap();
+
+                        for iline in tokenize_by(&ilines, "\n", -1, true, true) {
+                            stats.count_stderr(context, &iline);
+
+                            my_println(
+                                log_handle,
+                                &true,
+                                &true,
+                                &format!("{} stderr: {}", context, iline),
+                            )?;
+                        }
+                    }
                     Some(ilines) => {
-                        let parsed_lines = SternJSON::parse(&ilines, Some(regex));
-
-                        print_parsed_stern_json(
-                            streamer,
-                            &parsed_lines,
-                            arg_skip_invalid_messages,
-                            arg_blank_line_after_entry,
-                            &arg_include_container,
-                            arg_quiet,
-                            &arg_fix_up_messages,
-                            &arg_pretty_print_objects,
-                            &arg_space_after_message,
-                            message_regex,
+                        let mut parsed_lines = log_source.parse(streamer, &ilines, regex);
+                        let context = streamer.user_data.as_ref().unwrap();
+
+                        if let Some(supervisor) = &mut supervisor {
+                            parsed_lines = supervisor.observe(index, parsed_lines);
+                        }
+
+                        if let Some(joiner) = &mut pipeline.joiner {
+                            parsed_lines = joiner.push(context, parsed_lines);
+                        }
+
+                        dispatch_parsed_stern_json(
+                            context,
+                            parsed_lines,
+                            pipeline,
                             stats,
                             log_handle,
                         )?;
@@ -418,165 +1040,464 @@ fn gather_logs_from_multi_streamer(
             }
         }
 
-        std::thread::sleep(Duration::from_secs(0));
+        stats.count_replaced_chars(multi_streamer.take_replaced_chars());
+
+        if let Some(supervisor) = &mut supervisor {
+            restart_exited_streamers(log_source, multi_streamer, supervisor, stats, log_handle)?;
+        }
+
+        if let Some(joiner) = &mut pipeline.joiner {
+            dispatch_joined_stern_json(joiner.pop_ready(), pipeline, stats, log_handle)?;
+        }
+
+        if let Some(merger) = &mut pipeline.merger {
+            print_merged_stern_json(merger.pop_ready(), pipeline, stats, log_handle)?;
+        }
+
+        if let Some(grouper) = &mut pipeline.grouper {
+            print_entry_groups(
+                grouper.pop_ready(),
+                &grouper.field,
+                pipeline.print_options,
+                pipeline.message_regex,
+                stats,
+                log_handle,
+            )?;
+        }
+
+        // timers of restarts, merge window and groups still have to tick without new data
+        if supervisor.is_some() || !multi_streamer.is_eof() {
+            multi_streamer.wait_for_data(Duration::from_millis(100));
+        }
+    }
+
+    if let Some(joiner) = &mut pipeline.joiner {
+        dispatch_joined_stern_json(joiner.pop_all(), pipeline, stats, log_handle)?;
+    }
+
+    if let Some(merger) = &mut pipeline.merger {
+        print_merged_stern_json(merger.pop_all(), pipeline, stats, log_handle)?;
+    }
+
+    for streamer in multi_streamer.get_streamers() {
+        if let Some(exit_status) = streamer.wait_exit_status() {
+            stats.count_exit(
+                streamer.user_data.as_deref().unwrap_or_default(),
+                &exit_status,
+            );
+        }
+    }
+
+    return Ok(());
+}
+
+/// Passes parsed entries of a context to the merger if there is one, prints them otherwise.
+fn dispatch_parsed_stern_json(
+    context: &str,
+    parsed_lines: Vec<SternJSON>,
+    pipeline: &mut EntryPipeline,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    match &mut pipeline.merger {
+        Some(merger) => merger.push(context, parsed_lines),
+        None => print_parsed_stern_json(
+            context,
+            &parsed_lines,
+            pipeline.print_options,
+            pipeline.message_regex,
+            &mut pipeline.grouper,
+            stats,
+            log_handle,
+        )?,
+    }
+
+    return Ok(());
+}
+
+fn dispatch_joined_stern_json(
+    joined_lines: Vec<(String, SternJSON)>,
+    pipeline: &mut EntryPipeline,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    for (context, json) in joined_lines {
+        dispatch_parsed_stern_json(&context, vec![json], pipeline, stats, log_handle)?;
+    }
+
+    return Ok(());
+}
+
+fn print_merged_stern_json(
+    merged_lines: Vec<(String, SternJSON)>,
+    pipeline: &mut EntryPipeline,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    for (context, json) in merged_lines {
+        print_parsed_stern_json(
+            &context,
+            &[json],
+            pipeline.print_options,
+            pipeline.message_regex,
+            &mut pipeline.grouper,
+            stats,
+            log_handle,
+        )?;
+    }
+
+    return Ok(());
+}
+
+fn print_parsed_stern_json(
+    context: &str,
+    parsed_lines: &[SternJSON],
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+    grouper: &mut Option<EntryGrouper>,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    let arg_skip_invalid_messages = print_options.skip_invalid_messages;
+    let arg_include_container = &print_options.include_container;
+
+    for ipar in parsed_lines {
+        stats.count_total(context, ipar);
+
+        if !is_grep_match(ipar, print_options, message_regex) {
+            stats.count_filtered_out(context, ipar);
+            continue;
+        }
+
+        if !ipar.is_valid && arg_skip_invalid_messages {
+            continue;
+        }
+
+        if ipar.is_valid {
+            if let Some(include_container) = &arg_include_container {
+                if !include_container.contains(&ipar.container_name) {
+                    stats.count_filtered_out(context, ipar);
+                    continue;
+                }
+            }
+
+            if let Some(min_level) = print_options.min_level {
+                // entries without a known level are never filtered out
+                if ipar.level.is_some_and(|level| level < min_level) {
+                    stats.count_filtered_out(context, ipar);
+                    continue;
+                }
+            }
+        }
+
+        if !print_options.where_predicates.is_empty() {
+            // entries without a JSON message, invalid ones too, never match
+            let is_match = match &ipar.internal_json_message {
+                Some(internal_json_message) if ipar.is_valid => print_options
+                    .where_predicates
+                    .iter()
+                    .all(|predicate| predicate.is_match(internal_json_message)),
+                _ => false,
+            };
+
+            if !is_match {
+                stats.count_filtered_out(context, ipar);
+                continue;
+            }
+        }
+
+        if let Some(grouper) = grouper {
+            // entries are printed later, together with the rest of their group
+            match get_entry_field_value(ipar, &grouper.field, &print_options.recognizers) {
+                Some(value) if grouper.accepts(&value) => {
+                    count_entry_exception(context, ipar, print_options, stats);
+                    grouper.push(&value, context, ipar.clone())
+                }
+                _ => stats.count_filtered_out(context, ipar),
+            }
+
+            continue;
+        }
+
+        count_entry_exception(context, ipar, print_options, stats);
+        print_stern_json_entry(context, ipar, print_options, message_regex, log_handle)?;
+
+        stats.count_printed(context, ipar);
+    }
+
+    return Ok(());
+}
+
+fn print_entry_groups(
+    groups: Vec<EntryGroup>,
+    field: &str,
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+    stats: &mut Stats,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    for igroup in groups {
+        my_println(
+            log_handle,
+            &true,
+            &print_options.quiet.not(),
+            &format!(
+                "=== {}: {} ({} entries, contexts: {})",
+                field,
+                igroup.value,
+                igroup.entries.len(),
+                igroup.contexts.join(", ")
+            ),
+        )?;
+
+        for (context, json) in igroup.into_timeline() {
+            print_stern_json_entry(&context, &json, print_options, message_regex, log_handle)?;
+
+            stats.count_printed(&context, &json);
+        }
+
+        my_println(log_handle, &true, &print_options.quiet.not(), &"".into())?;
+    }
+
+    return Ok(());
+}
+
+fn print_stern_json_entry(
+    context: &str,
+    json: &SternJSON,
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    if print_options.output_format == OutputFormat::JsonLines {
+        return print_json_line(context, json, print_options, message_regex, log_handle);
+    }
+
+    if !json.is_valid {
+        return print_raw_message(
+            &mut json.raw.to_string(),
+            print_options,
+            message_regex,
+            log_handle,
+        );
+    }
+
+    let entry_line = EntryLine {
+        template: &print_options.template,
+        context,
+        json,
+    };
+
+    if let Some(internal_json_message) = &json.internal_json_message {
+        let printed = match print_options.recognizers.find(internal_json_message) {
+            Some(recognizer) => print_recognized_json_message(
+                &entry_line,
+                recognizer,
+                internal_json_message,
+                print_options,
+                message_regex,
+                log_handle,
+            )?,
+            None => false,
+        };
+
+        if !printed {
+            print_internal_json_message(
+                &entry_line,
+                internal_json_message,
+                print_options,
+                log_handle,
+            )?;
+        }
+    } else {
+        print_message(
+            &entry_line,
+            &mut json.message.to_string(),
+            print_options,
+            message_regex,
+            log_handle,
+        )?;
+    }
+
+    return Ok(());
+}
+
+/// Returns value of a field of the JSON message for `--group-by`, `request_id`
+/// is taken by the recognizer's request id key.
+fn get_entry_field_value(
+    json: &SternJSON,
+    field: &str,
+    recognizers: &RecognizerRegistry,
+) -> Option<String> {
+    let internal_json_message = json.internal_json_message.as_ref()?;
+    let mut path = field;
+
+    if field == "request_id" {
+        if let Some(recognizer) = recognizers.find(internal_json_message) {
+            path = &recognizer.request_id_key;
+        }
+    }
+
+    return match get_value_by_path(internal_json_message, path)? {
+        Value::Null => None,
+        value => Some(value_to_string(value)),
+    };
+}
+
+/// Checks `--grep` and `--grep-v` against the fixed-up message
+/// and with `--grep-raw` also against the raw line.
+fn is_grep_match(
+    json: &SternJSON,
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+) -> bool {
+    if print_options.grep.is_empty() && print_options.grep_v.is_empty() {
+        return true;
+    }
+
+    let recognizer = match &json.internal_json_message {
+        Some(internal_json_message) => print_options.recognizers.find(internal_json_message),
+        None => None,
+    };
+
+    let mut message = get_entry_message(json, recognizer);
+
+    if let Some(formatted_message) = fix_up_message(
+        &message,
+        &print_options.fix_up_messages,
+        &false,
+        &false,
+        message_regex,
+    ) {
+        message = formatted_message;
+    }
+
+    let mut haystacks = vec![message.as_str()];
+
+    if print_options.grep_raw {
+        haystacks.push(json.raw.as_str());
+    }
+
+    let is_match = |regex: &Regex| haystacks.iter().any(|haystack| regex.is_match(haystack));
+
+    if !print_options.grep.is_empty() && !print_options.grep.iter().any(is_match) {
+        return false;
     }
 
-    return Ok(());
+    return !print_options.grep_v.iter().any(is_match);
 }
 
-fn print_parsed_stern_json(
-    streamer: &CommandStreamer,
-    parsed_lines: &Vec<SternJSON>,
-    arg_skip_invalid_messages: bool,
-    arg_blank_line_after_entry: bool,
-    arg_include_container: &Option<Vec<String>>,
-    arg_quiet: &bool,
-    arg_fix_up_messages: &bool,
-    arg_pretty_print_objects: &bool,
-    arg_space_after_message: &bool,
-    message_regex: &MessageRegEx,
+/// Returns message and stack trace of an entry with `exc_info` (or other key
+/// of its recognizer) or of a plain text entry joined from many lines.
+/// Adds the exception of an entry which passed the filters to the exception summary.
+fn count_entry_exception(
+    context: &str,
+    json: &SternJSON,
+    print_options: &PrintOptions,
     stats: &mut Stats,
-    log_handle: &mut Option<File>,
-) -> Result<()> {
-    let context = streamer.user_data.as_ref().unwrap();
-
-    for ipar in parsed_lines {
-        stats.total_logs += 1;
-
-        if !ipar.is_valid {
-            if !arg_skip_invalid_messages {
-                print_raw_message(
-                    &mut ipar.raw.to_string(),
-                    arg_blank_line_after_entry,
-                    arg_quiet,
-                    arg_fix_up_messages,
-                    arg_pretty_print_objects,
-                    arg_space_after_message,
-                    message_regex,
-                    log_handle,
-                )?;
+) {
+    if let Some((message, exception)) = get_entry_exception(json, &print_options.recognizers) {
+        stats
+            .exceptions
+            .add(context, json.datetime, &message, &exception);
+    }
+}
 
-                stats.printed_logs += 1;
-            }
+fn get_entry_exception(
+    json: &SternJSON,
+    recognizers: &RecognizerRegistry,
+) -> Option<(String, String)> {
+    if !json.is_valid {
+        return None;
+    }
 
-            continue;
+    let internal_json_message = match &json.internal_json_message {
+        Some(internal_json_message) => internal_json_message,
+        None if json.message.contains('\n') => {
+            return Some((json.message.to_string(), json.message.to_string()))
         }
+        None => return None,
+    };
 
-        // valid
-        if let Some(include_container) = &arg_include_container {
-            if !include_container.contains(&ipar.container_name) {
-                stats.filtered_out_logs += 1;
-                continue;
-            }
-        }
+    let recognizer = recognizers.find(internal_json_message)?;
 
-        let basics = format!(
-            "{} {} {} {}    ",
-            context, ipar.pod_name, ipar.container_name, ipar.timestamp,
-        );
+    if !matches!(recognizer.renderer, Renderer::ExcInfo) {
+        return None;
+    }
 
-        if let Some(internal_json_message) = &ipar.internal_json_message {
-            let mut request_id = None;
+    let exception = match get_value_by_path(internal_json_message, &recognizer.exc_info_key)? {
+        Value::String(exception) => exception.to_string(),
+        _ => return None,
+    };
 
-            if internal_json_message.contains_key("request_id") {
-                request_id = Some(internal_json_message["request_id"].to_string());
-            }
+    return Some((get_entry_message(json, Some(recognizer)), exception));
+}
 
-            if internal_json_message.contains_key("exc_info")
-                && internal_json_message.contains_key("message")
-            {
-                if !print_json_exc_info_message(
-                    &basics,
-                    &request_id,
-                    internal_json_message,
-                    arg_blank_line_after_entry,
-                    arg_quiet,
-                    arg_fix_up_messages,
-                    arg_pretty_print_objects,
-                    arg_space_after_message,
-                    message_regex,
-                    log_handle,
-                )? {
-                    print_internal_json_message(
-                        &basics,
-                        internal_json_message,
-                        arg_blank_line_after_entry,
-                        arg_quiet,
-                        log_handle,
-                    )?;
-                }
-            } else if internal_json_message.contains_key("message") {
-                if !print_json_message(
-                    &basics,
-                    internal_json_message,
-                    arg_blank_line_after_entry,
-                    arg_quiet,
-                    arg_fix_up_messages,
-                    arg_pretty_print_objects,
-                    arg_space_after_message,
-                    message_regex,
-                    log_handle,
-                )? {
-                    print_internal_json_message(
-                        &basics,
-                        internal_json_message,
-                        arg_blank_line_after_entry,
-                        arg_quiet,
-                        log_handle,
-                    )?;
-                }
-            } else if internal_json_message.contains_key("downstream_local_address")
-                && internal_json_message.contains_key("method")
-                && internal_json_message.contains_key("path")
-                && internal_json_message.contains_key("protocol")
-                && internal_json_message.contains_key("response_code")
-                && internal_json_message.contains_key("bytes_sent")
-                && internal_json_message.contains_key("bytes_received")
-                && internal_json_message.contains_key("duration")
-                && internal_json_message.contains_key("upstream_service_time")
-            {
-                if !print_json_proxy(
-                    &basics,
-                    &request_id,
-                    internal_json_message,
-                    arg_blank_line_after_entry,
-                    arg_quiet,
-                    log_handle,
-                )? {
-                    print_internal_json_message(
-                        &basics,
-                        internal_json_message,
-                        arg_blank_line_after_entry,
-                        arg_quiet,
-                        log_handle,
-                    )?;
-                }
-            } else {
-                print_internal_json_message(
-                    &basics,
-                    internal_json_message,
-                    arg_blank_line_after_entry,
-                    arg_quiet,
-                    log_handle,
-                )?;
-            }
-        } else {
-            print_message(
-                &basics,
-                &mut ipar.message.to_string(),
-                arg_blank_line_after_entry,
-                arg_quiet,
-                arg_fix_up_messages,
-                arg_pretty_print_objects,
-                arg_space_after_message,
-                message_regex,
-                log_handle,
-            )?;
+fn get_entry_message(json: &SternJSON, recognizer: Option<&Recognizer>) -> String {
+    if !json.is_valid {
+        return json.raw.to_string();
+    }
+
+    if let Some(internal_json_message) = &json.internal_json_message {
+        let message_key = match recognizer {
+            Some(recognizer) => recognizer.message_key.as_str(),
+            None => "message",
+        };
+
+        if let Some(Value::String(message)) = get_value_by_path(internal_json_message, message_key)
+        {
+            return message.to_string();
         }
+    }
+
+    return json.message.to_string();
+}
 
-        stats.printed_logs += 1;
+fn print_json_line(
+    context: &str,
+    json: &SternJSON,
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+    log_handle: &mut Option<File>,
+) -> Result<()> {
+    let arg_quiet = &print_options.quiet;
+    let arg_fix_up_messages = &print_options.fix_up_messages;
+    let mut recognizer = None;
+    let mut request_id = None;
+
+    if let Some(internal_json_message) = &json.internal_json_message {
+        recognizer = print_options.recognizers.find(internal_json_message);
+        request_id = get_value_by_path(
+            internal_json_message,
+            match recognizer {
+                Some(recognizer) => &recognizer.request_id_key,
+                None => "request_id",
+            },
+        );
+    }
+
+    let mut message = get_entry_message(json, recognizer);
+
+    if let Some(formatted_message) =
+        fix_up_message(&message, arg_fix_up_messages, &false, &false, message_regex)
+    {
+        message = formatted_message;
     }
 
+    my_println_reserved(
+        log_handle,
+        &true,
+        &arg_quiet.not(),
+        &entry_to_json_line(
+            context,
+            json,
+            &message,
+            recognizer.map(|recognizer| recognizer.name.as_str()),
+            json.level.map(|level| level.as_str()),
+            request_id,
+        ),
+    )?;
+
     return Ok(());
 }
 
@@ -679,14 +1600,16 @@ fn fix_up_message(
 
 fn print_raw_message(
     message: &mut String,
-    arg_blank_line_after_entry: bool,
-    arg_quiet: &bool,
-    arg_fix_up_messages: &bool,
-    arg_pretty_print_objects: &bool,
-    arg_space_after_message: &bool,
+    print_options: &PrintOptions,
     message_regex: &MessageRegEx,
     log_handle: &mut Option<File>,
 ) -> Result<()> {
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+    let arg_fix_up_messages = &print_options.fix_up_messages;
+    let arg_pretty_print_objects = &print_options.pretty_print_objects;
+    let arg_space_after_message = &print_options.space_after_message;
+
     if let Some(formatted_message) = fix_up_message(
         message,
         arg_fix_up_messages,
@@ -708,17 +1631,19 @@ fn print_raw_message(
 }
 
 fn print_internal_json_message(
-    basics: &String,
+    entry_line: &EntryLine,
     internal_json_message: &HashMap<String, Value>,
-    arg_blank_line_after_entry: bool,
-    arg_quiet: &bool,
+    print_options: &PrintOptions,
     log_handle: &mut Option<File>,
 ) -> Result<()> {
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+
     my_println(
         log_handle,
         &true,
         &arg_quiet.not(),
-        &format!("{}{:?}", basics, internal_json_message),
+        &entry_line.format(&serde_json::to_string(internal_json_message)?),
     )?;
 
     if arg_blank_line_after_entry {
@@ -729,16 +1654,18 @@ fn print_internal_json_message(
 }
 
 fn print_message(
-    basics: &String,
+    entry_line: &EntryLine,
     message: &mut String,
-    arg_blank_line_after_entry: bool,
-    arg_quiet: &bool,
-    arg_fix_up_messages: &bool,
-    arg_pretty_print_objects: &bool,
-    arg_space_after_message: &bool,
+    print_options: &PrintOptions,
     message_regex: &MessageRegEx,
     log_handle: &mut Option<File>,
 ) -> Result<()> {
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+    let arg_fix_up_messages = &print_options.fix_up_messages;
+    let arg_pretty_print_objects = &print_options.pretty_print_objects;
+    let arg_space_after_message = &print_options.space_after_message;
+
     if let Some(formatted_message) = fix_up_message(
         message,
         arg_fix_up_messages,
@@ -754,7 +1681,7 @@ fn print_message(
         log_handle,
         &true,
         &arg_quiet.not(),
-        &format!("{}{}", basics, message),
+        &entry_line.format(message),
     )?;
 
     if arg_blank_line_after_entry {
@@ -764,26 +1691,124 @@ fn print_message(
     return Ok(());
 }
 
+fn print_recognized_json_message(
+    entry_line: &EntryLine,
+    recognizer: &Recognizer,
+    internal_json_message: &HashMap<String, Value>,
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+    log_handle: &mut Option<File>,
+) -> Result<bool> {
+    let mut request_id = None;
+
+    if let Some(value) = get_value_by_path(internal_json_message, &recognizer.request_id_key) {
+        request_id = Some(value.to_string());
+    }
+
+    return match &recognizer.renderer {
+        Renderer::ExcInfo => print_json_exc_info_message(
+            entry_line,
+            &request_id,
+            internal_json_message,
+            recognizer,
+            print_options,
+            message_regex,
+            log_handle,
+        ),
+        Renderer::Message => print_json_message(
+            entry_line,
+            internal_json_message,
+            &recognizer.message_key,
+            print_options,
+            message_regex,
+            log_handle,
+        ),
+        Renderer::Proxy => print_json_proxy(
+            entry_line,
+            &request_id,
+            internal_json_message,
+            print_options,
+            log_handle,
+        ),
+        Renderer::Template(template) => print_json_template(
+            entry_line,
+            &request_id,
+            template,
+            print_options,
+            message_regex,
+            log_handle,
+        ),
+        Renderer::Json => Ok(false),
+    };
+}
+
+fn print_json_template(
+    entry_line: &EntryLine,
+    request_id: &Option<String>,
+    template: &Template,
+    print_options: &PrintOptions,
+    message_regex: &MessageRegEx,
+    log_handle: &mut Option<File>,
+) -> Result<bool> {
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+    let arg_fix_up_messages = &print_options.fix_up_messages;
+    let arg_pretty_print_objects = &print_options.pretty_print_objects;
+    let arg_space_after_message = &print_options.space_after_message;
+
+    let mut message = entry_line.render(template, None);
+
+    if let Some(formatted_message) = fix_up_message(
+        &message,
+        arg_fix_up_messages,
+        arg_pretty_print_objects,
+        arg_space_after_message,
+        message_regex,
+    ) {
+        message = formatted_message;
+    }
+
+    if let Some(request_id) = request_id {
+        message.push_str(&format!("    (request_id: {})", request_id));
+    }
+
+    my_println(
+        log_handle,
+        &true,
+        &arg_quiet.not(),
+        &entry_line.format(&message),
+    )?;
+
+    if arg_blank_line_after_entry {
+        my_println(log_handle, &true, &arg_quiet.not(), &"".into())?;
+    }
+
+    return Ok(true);
+}
+
 fn print_json_exc_info_message(
-    basics: &String,
+    entry_line: &EntryLine,
     request_id: &Option<String>,
     internal_json_message: &HashMap<String, Value>,
-    arg_blank_line_after_entry: bool,
-    arg_quiet: &bool,
-    arg_fix_up_messages: &bool,
-    arg_pretty_print_objects: &bool,
-    arg_space_after_message: &bool,
+    recognizer: &Recognizer,
+    print_options: &PrintOptions,
     message_regex: &MessageRegEx,
     log_handle: &mut Option<File>,
 ) -> Result<bool> {
-    let mut exc_info = internal_json_message["exc_info"]
-        .as_str()
-        .unwrap()
-        .to_string();
-    let mut message = internal_json_message["message"]
-        .as_str()
-        .unwrap()
-        .to_string();
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+    let arg_fix_up_messages = &print_options.fix_up_messages;
+    let arg_pretty_print_objects = &print_options.pretty_print_objects;
+    let arg_space_after_message = &print_options.space_after_message;
+
+    let mut exc_info = match get_value_by_path(internal_json_message, &recognizer.exc_info_key) {
+        Some(Value::String(exc_info)) => exc_info.to_string(),
+        _ => return Ok(false),
+    };
+    let mut message = match get_value_by_path(internal_json_message, &recognizer.message_key) {
+        Some(Value::String(message)) => message.to_string(),
+        _ => return Ok(false),
+    };
 
     if let Some(formatted_exc_info) = fix_up_message(
         &exc_info,
@@ -805,16 +1830,26 @@ fn print_json_exc_info_message(
         message = formatted_message;
     }
 
-    let mut line0 = format!("{}{}", basics, exc_info);
-    let mut line1 = format!("{}{}", basics, message);
+    let mut line0 = exc_info;
+    let mut line1 = message;
 
     if let Some(request_id) = request_id {
         line0.push_str(&format!("    (request_id: {})", request_id));
         line1.push_str(&format!("    (request_id: {})", request_id));
     }
 
-    my_println(log_handle, &true, &arg_quiet.not(), &format!("{}", line0))?;
-    my_println(log_handle, &true, &arg_quiet.not(), &format!("{}", line1))?;
+    my_println(
+        log_handle,
+        &true,
+        &arg_quiet.not(),
+        &entry_line.format(&line0),
+    )?;
+    my_println(
+        log_handle,
+        &true,
+        &arg_quiet.not(),
+        &entry_line.format(&line1),
+    )?;
 
     if arg_blank_line_after_entry {
         my_println(log_handle, &true, &arg_quiet.not(), &"".into())?;
@@ -824,30 +1859,28 @@ fn print_json_exc_info_message(
 }
 
 fn print_json_proxy(
-    basics: &String,
+    entry_line: &EntryLine,
     request_id: &Option<String>,
     internal_json_message: &HashMap<String, Value>,
-    arg_blank_line_after_entry: bool,
-    arg_quiet: &bool,
+    print_options: &PrintOptions,
     log_handle: &mut Option<File>,
 ) -> Result<bool> {
-    let downstream_local_address = internal_json_message["downstream_local_address"]
-        .as_str()
-        .unwrap();
-    let method = internal_json_message["method"].as_str().unwrap();
-    let path = internal_json_message["path"].as_str().unwrap();
-    let protocol = internal_json_message["protocol"].as_str().unwrap();
-    let response_code = internal_json_message["response_code"].as_str().unwrap();
-    let bytes_sent = internal_json_message["bytes_sent"].as_str().unwrap();
-    let bytes_received = internal_json_message["bytes_received"].as_str().unwrap();
-    let duration = internal_json_message["duration"].as_str().unwrap();
-    let upstream_service_time = internal_json_message["upstream_service_time"]
-        .as_str()
-        .unwrap();
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+
+    let downstream_local_address =
+        value_to_string(&internal_json_message["downstream_local_address"]);
+    let method = value_to_string(&internal_json_message["method"]);
+    let path = value_to_string(&internal_json_message["path"]);
+    let protocol = value_to_string(&internal_json_message["protocol"]);
+    let response_code = value_to_string(&internal_json_message["response_code"]);
+    let bytes_sent = value_to_string(&internal_json_message["bytes_sent"]);
+    let bytes_received = value_to_string(&internal_json_message["bytes_received"]);
+    let duration = value_to_string(&internal_json_message["duration"]);
+    let upstream_service_time = value_to_string(&internal_json_message["upstream_service_time"]);
 
     let mut line0 = format!(
-        "{}{} \"{} {} {}\" {}, {} {}, {} {}",
-        basics,
+        "{} \"{} {} {}\" {}, {} {}, {} {}",
         downstream_local_address,
         method,
         path,
@@ -863,7 +1896,12 @@ fn print_json_proxy(
         line0.push_str(&format!("    (request_id: {})", request_id));
     }
 
-    my_println(log_handle, &true, &arg_quiet.not(), &format!("{}", line0))?;
+    my_println(
+        log_handle,
+        &true,
+        &arg_quiet.not(),
+        &entry_line.format(&line0),
+    )?;
 
     if arg_blank_line_after_entry {
         my_println(log_handle, &true, &arg_quiet.not(), &"".into())?;
@@ -873,20 +1911,23 @@ fn print_json_proxy(
 }
 
 fn print_json_message(
-    basics: &String,
+    entry_line: &EntryLine,
     internal_json_message: &HashMap<String, Value>,
-    arg_blank_line_after_entry: bool,
-    arg_quiet: &bool,
-    arg_fix_up_messages: &bool,
-    arg_pretty_print_objects: &bool,
-    arg_space_after_message: &bool,
+    message_key: &str,
+    print_options: &PrintOptions,
     message_regex: &MessageRegEx,
     log_handle: &mut Option<File>,
 ) -> Result<bool> {
-    let mut message = internal_json_message["message"]
-        .as_str()
-        .unwrap()
-        .to_string();
+    let arg_blank_line_after_entry = print_options.blank_line_after_entry;
+    let arg_quiet = &print_options.quiet;
+    let arg_fix_up_messages = &print_options.fix_up_messages;
+    let arg_pretty_print_objects = &print_options.pretty_print_objects;
+    let arg_space_after_message = &print_options.space_after_message;
+
+    let mut message = match get_value_by_path(internal_json_message, message_key) {
+        Some(Value::String(message)) => message.to_string(),
+        _ => return Ok(false),
+    };
 
     if let Some(formatted_message) = fix_up_message(
         &message,
@@ -902,7 +1943,7 @@ fn print_json_message(
         log_handle,
         &true,
         &arg_quiet.not(),
-        &format!("{}{}", basics, message),
+        &entry_line.format(&message),
     )?;
 
     if arg_blank_line_after_entry {
@@ -912,34 +1953,282 @@ fn print_json_message(
     return Ok(true);
 }
 
-fn parse_args() -> Result<ArgParser> {
-    let parsed = ArgParser::new(
-        &vec![
-            "--context",
-            "-c",
-            "--stern-defaults",
-            "-d",
-            "--all-at-once",
-            "-a",
-            "--skip-invalid-messages",
-            "-s",
-            "--blank-line-after-entry",
-            "-b",
-            "--include-container",
-            "-i",
-            "--save",
-            "-f",
-            "--work-dir",
-            "-w",
-            "--fix-up-messages",
-            "-m",
-            "--pretty-print-objects",
-            "-p",
-            "--since",
-            "-t",
-            "--space-after-message",
-            "-r",
-        ],
+fn parse_args() -> Result<(ArgParser, Config, BTreeMap<String, OptionSource>)> {
+    let config = Config::load()?;
+    let mut default_kv_args: BTreeMap<String, String> = BTreeMap::from([
+        ("--context", "default"),
+        ("--stern-defaults", "true"),
+        ("--all-at-once", "false"),
+        ("--skip-invalid-messages", "false"),
+        ("--blank-line-after-entry", "false"),
+        ("--fix-up-messages", "true"),
+        ("--pretty-print-objects", "false"),
+        ("--since", "1h"),
+        ("--space-after-message", "true"),
+        ("--log-source", "stern"),
+        ("--merge-by-time", "false"),
+        ("--merge-window", "2s"),
+        ("--group-window", "2s"),
+        ("--restart", "true"),
+        ("--lossy-utf8", "true"),
+        ("--output", "text"),
+        ("--grep-raw", "false"),
+        ("--join-multi-line", "false"),
+        ("--multi-line-timeout", "500ms"),
+        ("--exception-summary", "true"),
+        ("--stats-format", "text"),
+        ("--template", DEFAULT_TEMPLATE),
+    ])
+    .into_iter()
+    .map(|(iarg, iarg_value)| (iarg.to_string(), iarg_value.to_string()))
+    .collect();
+
+    let cli_args = new_arg_parser(&BTreeMap::new());
+    let mut kv_args_sources: BTreeMap<String, OptionSource> = default_kv_args
+        .keys()
+        .map(|iarg| (iarg.to_string(), OptionSource::Default))
+        .collect();
+
+    // config and environment values are merged as defaults, so explicit options override them
+    let arg_profile = cli_args
+        .get_kv_arg_string("--profile", false, false)
+        .or_else(|| OptionSource::get_env_value("--profile"));
+    let config_kv_args = config.get_kv_args(&arg_profile)?;
+    // every value of a repeatable option, given to the parser unless set on the command line
+    let mut default_kv_args_values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for iarg in config_kv_args.keys() {
+        if !get_supported_kv_args().contains(&iarg.as_str()) {
+            return Err(Error::msg(format!(
+                "unknown option \"{}\" in config {}",
+                iarg.trim_start_matches('-'),
+                config
+                    .pathnames
+                    .iter()
+                    .map(|ipathname| ipathname.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )));
+        }
+    }
+
+    for iarg in config_kv_args.keys() {
+        let source = match &arg_profile {
+            Some(arg_profile)
+                if config
+                    .profiles
+                    .get(arg_profile)
+                    .is_some_and(|profile| profile.contains_key(iarg.trim_start_matches('-'))) =>
+            {
+                OptionSource::Profile(arg_profile.to_string())
+            }
+            _ => OptionSource::Config,
+        };
+
+        kv_args_sources.insert(iarg.to_string(), source);
+    }
+
+    for (iarg, ivalues) in config_kv_args {
+        if get_repeatable_kv_args().contains(&iarg.as_str()) {
+            default_kv_args.insert(
+                iarg.to_string(),
+                ivalues.last().cloned().unwrap_or_default(),
+            );
+            default_kv_args_values.insert(iarg, ivalues);
+        } else {
+            // lists like --context are comma separated
+            default_kv_args.insert(iarg, ivalues.join(","));
+        }
+    }
+
+    for iarg in get_supported_kv_args() {
+        if !iarg.starts_with("--") {
+            continue;
+        }
+
+        if let Some(value) = OptionSource::get_env_value(iarg) {
+            default_kv_args.insert(iarg.to_string(), value);
+            default_kv_args_values.remove(iarg);
+            kv_args_sources.insert(
+                iarg.to_string(),
+                OptionSource::Env(OptionSource::get_env_name(iarg)),
+            );
+        }
+    }
+
+    for iarg in cli_args.kv_args.keys() {
+        kv_args_sources.insert(iarg.to_string(), OptionSource::CommandLine);
+    }
+
+    let mut parsed = new_arg_parser(&default_kv_args);
+
+    for (iarg, ivalues) in default_kv_args_values {
+        parsed.kv_args_values.entry(iarg).or_insert(ivalues);
+    }
+
+    parsed.get_bool_kv_arg("--stern-defaults", false)?;
+    parsed.get_bool_kv_arg("--all-at-once", false)?;
+    parsed.get_bool_kv_arg("--skip-invalid-messages", false)?;
+    parsed.get_bool_kv_arg("--blank-line-after-entry", false)?;
+    parsed.get_bool_kv_arg("--fix-up-messages", false)?;
+    parsed.get_bool_kv_arg("--pretty-print-objects", false)?;
+    parsed.get_bool_kv_arg("--space-after-message", false)?;
+    parsed.get_bool_kv_arg("--merge-by-time", false)?;
+    parsed.get_bool_kv_arg("--restart", false)?;
+    parsed.get_bool_kv_arg("--lossy-utf8", false)?;
+
+    if let Some(arg_parallel) = parsed.get_kv_arg_string("--parallel", false, false) {
+        match arg_parallel.parse::<usize>() {
+            Ok(parallel) if parallel > 0 => {}
+            _ => {
+                return Err(Error::msg(format!(
+                    "\"{}\" is not a valid --parallel, use a number greater than 0",
+                    arg_parallel
+                )))
+            }
+        }
+
+        if parsed.get_bool_kv_arg("--all-at-once", false)? {
+            return Err(Error::msg("use either --all-at-once or --parallel"));
+        }
+    }
+
+    string_to_duration(
+        &parsed
+            .get_kv_arg_string("--merge-window", false, false)
+            .unwrap(),
+    )?;
+    string_to_duration(
+        &parsed
+            .get_kv_arg_string("--group-window", false, false)
+            .unwrap(),
+    )?;
+    OutputFormat::parse(&parsed.get_kv_arg_string("--output", false, false).unwrap())?;
+    Template::parse(
+        &parsed
+            .get_kv_arg_string("--template", false, false)
+            .unwrap(),
+    )?;
+
+    if let Some(arg_min_level) = parsed.get_kv_arg_string("--min-level", false, false) {
+        LogLevel::parse_arg(&arg_min_level)?;
+    }
+
+    parsed.get_bool_kv_arg("--grep-raw", false)?;
+    parsed.get_bool_kv_arg("--join-multi-line", false)?;
+    parsed.get_bool_kv_arg("--exception-summary", false)?;
+    StatsFormat::parse(
+        &parsed
+            .get_kv_arg_string("--stats-format", false, false)
+            .unwrap(),
+    )?;
+    string_to_duration(
+        &parsed
+            .get_kv_arg_string("--multi-line-timeout", false, false)
+            .unwrap(),
+    )?;
+
+    for iregex in parsed
+        .get_kv_arg_values("--grep", false)
+        .iter()
+        .chain(parsed.get_kv_arg_values("--grep-v", false).iter())
+    {
+        Regex::new(iregex)?;
+    }
+
+    ContextPattern::parse_list(&tokenize_by(
+        &parsed.get_kv_arg_string("--context", false, false).unwrap(),
+        ",",
+        -1,
+        true,
+        true,
+    ))?;
+
+    for iexclude in parsed.get_kv_arg_values("--exclude-context", false) {
+        ContextPattern::parse_list(&tokenize_by(&iexclude, ",", -1, true, true))?;
+    }
+
+    for ipredicate in parsed.get_kv_arg_values("--where", false) {
+        FieldPredicate::parse(&ipredicate)?;
+    }
+
+    if !parsed.unknown_args.is_empty() {
+        return Err(Error::msg(format!(
+            "Unknown parameter(s): {:?}",
+            parsed.unknown_args
+        )));
+    }
+
+    return Ok((parsed, config, kv_args_sources));
+}
+
+fn get_supported_kv_args() -> Vec<&'static str> {
+    return vec![
+        "--context",
+        "-c",
+        "--exclude-context",
+        "--parallel",
+        "--restart",
+        "--lossy-utf8",
+        "--stern-defaults",
+        "-d",
+        "--all-at-once",
+        "-a",
+        "--skip-invalid-messages",
+        "-s",
+        "--blank-line-after-entry",
+        "-b",
+        "--include-container",
+        "-i",
+        "--save",
+        "-f",
+        "--work-dir",
+        "-w",
+        "--fix-up-messages",
+        "-m",
+        "--pretty-print-objects",
+        "-p",
+        "--since",
+        "-t",
+        "--space-after-message",
+        "-r",
+        "--log-source",
+        "-l",
+        "--from-file",
+        "-e",
+        "--file-context",
+        "-k",
+        "--merge-by-time",
+        "-j",
+        "--merge-window",
+        "--output",
+        "-o",
+        "--template",
+        "--recognizers",
+        "--min-level",
+        "--grep",
+        "--grep-v",
+        "--grep-raw",
+        "--where",
+        "--trace",
+        "--group-by",
+        "--group-window",
+        "--join-multi-line",
+        "--multi-line-timeout",
+        "--exception-summary",
+        "--stats-format",
+        "--profile",
+    ];
+}
+
+/// Options which can be given more than once, see `ArgParser::get_kv_arg_values`.
+fn get_repeatable_kv_args() -> Vec<&'static str> {
+    return vec!["--exclude-context", "--grep", "--grep-v", "--where"];
+}
+
+fn new_arg_parser(default_kv_args: &BTreeMap<String, String>) -> ArgParser {
+    return ArgParser::new(
+        &get_supported_kv_args(),
         &vec![
             "--help", "-h", "--save", "-f", "--quiet", "-q", "--follow", "-g",
         ],
@@ -961,19 +2250,17 @@ fn parse_args() -> Result<ArgParser> {
             &["--since", "-t"],
             &["--space-after-message", "-r"],
             &["--follow", "-g"],
+            &["--log-source", "-l"],
+            &["--from-file", "-e"],
+            &["--file-context", "-k"],
+            &["--merge-by-time", "-j"],
+            &["--output", "-o"],
         ],
         &vec![],
-        BTreeMap::from([
-            ("--context", "default"),
-            ("--stern-defaults", "true"),
-            ("--all-at-once", "false"),
-            ("--skip-invalid-messages", "false"),
-            ("--blank-line-after-entry", "false"),
-            ("--fix-up-messages", "true"),
-            ("--pretty-print-objects", "false"),
-            ("--since", "1h"),
-            ("--space-after-message", "true"),
-        ]),
+        default_kv_args
+            .iter()
+            .map(|(iarg, iarg_value)| (iarg.as_str(), iarg_value.as_str()))
+            .collect(),
         &vec![],
         BTreeMap::from([]),
         &vec![],
@@ -982,23 +2269,6 @@ fn parse_args() -> Result<ArgParser> {
         false,
         false,
     );
-
-    parsed.get_bool_kv_arg("--stern-defaults", false)?;
-    parsed.get_bool_kv_arg("--all-at-once", false)?;
-    parsed.get_bool_kv_arg("--skip-invalid-messages", false)?;
-    parsed.get_bool_kv_arg("--blank-line-after-entry", false)?;
-    parsed.get_bool_kv_arg("--fix-up-messages", false)?;
-    parsed.get_bool_kv_arg("--pretty-print-objects", false)?;
-    parsed.get_bool_kv_arg("--space-after-message", false)?;
-
-    if !parsed.unknown_args.is_empty() {
-        return Err(Error::msg(format!(
-            "Unknown parameter(s): {:?}",
-            parsed.unknown_args
-        )));
-    }
-
-    return Ok(parsed);
 }
 
 fn clean_args(args: &mut ArgParser) {
@@ -1010,7 +2280,8 @@ fn clean_args(args: &mut ArgParser) {
 }
 
 fn should_print_usages(args: &ArgParser) -> bool {
-    return args.args.contains(&"--help".to_string()) || args.ext_args.is_empty();
+    return args.args.contains(&"--help".to_string())
+        || (args.ext_args.is_empty() && !args.kv_args.contains_key("--from-file"));
 }
 
 fn _set_current_dir(arg_work_dir: &Option<String>) -> Result<Option<String>> {
@@ -1026,21 +2297,29 @@ fn _set_current_dir(arg_work_dir: &Option<String>) -> Result<Option<String>> {
 }
 
 fn main() -> Result<()> {
-    check_required_binaries()?;
-
-    let mut args = parse_args()?;
+    let (mut args, config, kv_args_sources) = parse_args()?;
 
     clean_args(&mut args);
 
     if should_print_usages(&args) {
         print_app_name(&mut None)?;
         print_app_info();
-        print_usages();
+        print_usages(&args, &kv_args_sources);
 
         return Ok(());
     }
 
     let arg_work_dir = args.get_kv_arg_string("--work-dir", false, false);
+    let arg_output = args.get_kv_arg_string("--output", false, false).unwrap();
+    let arg_stats_format = args
+        .get_kv_arg_string("--stats-format", false, false)
+        .unwrap();
+
+    // keep stdout clean for machine-readable output, entries or statistics
+    set_stdout_reserved(
+        OutputFormat::parse(&arg_output)? == OutputFormat::JsonLines
+            || StatsFormat::parse(&arg_stats_format)? == StatsFormat::Json,
+    );
 
     let work_dir = _set_current_dir(&arg_work_dir)?;
     let mut log_handle = open_log_file_handle(&args)?;
@@ -1075,6 +2354,24 @@ fn main() -> Result<()> {
         )?;
     }
 
+    for ipathname in config.pathnames.iter() {
+        my_println(
+            &mut log_handle,
+            &true,
+            &true,
+            &format!("Config: {}", ipathname.display()),
+        )?;
+    }
+
+    if let Some(arg_profile) = args.get_kv_arg_string("--profile", false, false) {
+        my_println(
+            &mut log_handle,
+            &true,
+            &true,
+            &format!("Profile: {}", arg_profile),
+        )?;
+    }
+
     if let Some(log_pathname) = log_pathname {
         my_println(
             &mut log_handle,
@@ -1084,7 +2381,7 @@ fn main() -> Result<()> {
         )?;
     }
 
-    let result = run(args, &mut log_handle);
+    let result = run(args, &config, &mut log_handle);
 
     my_println(
         &mut log_handle,
@@ -0,0 +1,355 @@
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::stern_json::SternJSON;
+
+// java/python/go stack trace lines which continue the previous entry
+const CONTINUATION: &str = r"^(?:at |\.\.\. \d+ (?:more|common frames omitted)|Caused by:|Suppressed:|Traceback \(most recent call last\):|File .+, line \d+|During handling of the above exception|The above exception was the direct cause|goroutine \d+ \[)";
+// python's last traceback line like "ValueError: message"
+const PYTHON_EXCEPTION: &str = r"^[\w.]+(?:Error|Exception|Warning|Exit|Interrupt)\b";
+// go's goroutine dump function line like "main.main()" or "net/http.(*conn).serve(0xc000)"
+const GO_FUNCTION: &str = r"^(?:created by )?[\w./*()-]+\(.*\)(?: in goroutine \d+)?$";
+
+struct PendingEntry {
+    context: String,
+    json: SternJSON,
+    is_traceback: bool,
+    is_goroutine: bool,
+    last_arrival: Instant,
+}
+
+/// Joins stack trace lines, printed by stern as separate entries, into one logical entry
+/// per (context, namespace, pod, container).
+///
+/// The last entry of each container is held until a line which does not continue it arrives,
+/// in follow mode also until no line continued it for the timeout.
+pub struct MultiLineJoiner {
+    timeout: Option<Duration>,
+    pending: HashMap<String, PendingEntry>,
+    continuation: Regex,
+    python_exception: Regex,
+    go_function: Regex,
+}
+
+impl MultiLineJoiner {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        return MultiLineJoiner {
+            timeout,
+            pending: HashMap::new(),
+            continuation: Regex::new(CONTINUATION).unwrap(),
+            python_exception: Regex::new(PYTHON_EXCEPTION).unwrap(),
+            go_function: Regex::new(GO_FUNCTION).unwrap(),
+        };
+    }
+
+    /// Returns entries of the context which are complete now, in order.
+    pub fn push(&mut self, context: &str, parsed_lines: Vec<SternJSON>) -> Vec<SternJSON> {
+        let mut completed = vec![];
+
+        for json in parsed_lines {
+            let key = format!(
+                "{}/{}/{}/{}",
+                context, json.namespace, json.pod_name, json.container_name
+            );
+
+            // only plain text messages can be a part of a stack trace
+            if !json.is_valid || json.internal_json_message.is_some() {
+                if let Some(pending) = self.pending.remove(&key) {
+                    completed.push(pending.json);
+                }
+
+                completed.push(json);
+                continue;
+            }
+
+            if let Some(pending) = self.pending.get_mut(&key) {
+                if Self::is_continuation(
+                    &json,
+                    pending,
+                    &self.continuation,
+                    &self.python_exception,
+                    &self.go_function,
+                ) {
+                    Self::append(pending, json);
+                    continue;
+                }
+
+                completed.push(self.pending.remove(&key).unwrap().json);
+            }
+
+            self.pending.insert(
+                key,
+                PendingEntry {
+                    context: context.to_string(),
+                    is_traceback: json.message.starts_with("Traceback "),
+                    is_goroutine: json.message.starts_with("goroutine "),
+                    json,
+                    last_arrival: Instant::now(),
+                },
+            );
+        }
+
+        return completed;
+    }
+
+    /// Returns entries which were not continued for the timeout.
+    pub fn pop_ready(&mut self) -> Vec<(String, SternJSON)> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return vec![],
+        };
+
+        let ready_keys: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.last_arrival.elapsed() >= timeout)
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        return self.pop_keys(ready_keys);
+    }
+
+    /// Returns all held entries.
+    pub fn pop_all(&mut self) -> Vec<(String, SternJSON)> {
+        let keys: Vec<String> = self.pending.keys().cloned().collect();
+
+        return self.pop_keys(keys);
+    }
+
+    fn pop_keys(&mut self, keys: Vec<String>) -> Vec<(String, SternJSON)> {
+        let mut popped: Vec<PendingEntry> = keys
+            .iter()
+            .filter_map(|key| self.pending.remove(key))
+            .collect();
+
+        popped.sort_by_key(|pending| pending.last_arrival);
+
+        return popped
+            .into_iter()
+            .map(|pending| (pending.context, pending.json))
+            .collect();
+    }
+
+    fn is_continuation(
+        json: &SternJSON,
+        pending: &PendingEntry,
+        continuation: &Regex,
+        python_exception: &Regex,
+        go_function: &Regex,
+    ) -> bool {
+        if json.message.is_empty() {
+            return pending.is_goroutine;
+        }
+
+        if json.is_indented || continuation.is_match(&json.message) {
+            return true;
+        }
+
+        if pending.is_traceback && python_exception.is_match(&json.message) {
+            return true;
+        }
+
+        return pending.is_goroutine && go_function.is_match(&json.message);
+    }
+
+    fn append(pending: &mut PendingEntry, json: SternJSON) {
+        let indent = if json.is_indented { "\t" } else { "" };
+
+        pending.json.message.push('\n');
+        pending.json.message.push_str(indent);
+        pending.json.message.push_str(&json.message);
+
+        pending.json.raw.push('\n');
+        pending.json.raw.push_str(&json.raw);
+
+        pending.is_traceback |= json.message.starts_with("Traceback ");
+        pending.is_goroutine |= json.message.starts_with("goroutine ");
+        pending.last_arrival = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stern_json_regex::SternJSONRegEx;
+
+    fn new_json(pod_name: &str, message: &str) -> SternJSON {
+        // after the timestamp the indentation of the message is kept, like in stern's output
+        return SternJSON::from_fields(
+            message,
+            &format!("01-01 00:00:01 {}", message),
+            "node",
+            "ns",
+            pod_name,
+            "app",
+            Some(&SternJSONRegEx::new()),
+        );
+    }
+
+    fn get_messages(completed: Vec<SternJSON>) -> Vec<String> {
+        return completed.into_iter().map(|ijson| ijson.message).collect();
+    }
+
+    #[test]
+    fn test_push_java_stack_trace() {
+        let mut joiner = MultiLineJoiner::new(None);
+
+        let completed = joiner.push(
+            "c1",
+            vec![
+                new_json("a", "java.lang.IllegalStateException: bad"),
+                new_json("a", "\tat a.B.c(B.java:1)"),
+                new_json("a", "Caused by: java.io.IOException: worse"),
+                new_json("a", "\tat a.B.d(B.java:2)"),
+                new_json("a", "\t... 3 more"),
+                new_json("a", "next"),
+            ],
+        );
+
+        assert_eq!(
+            get_messages(completed),
+            vec![
+                "java.lang.IllegalStateException: bad\n\tat a.B.c(B.java:1)\nCaused by: java.io.IOException: worse\n\tat a.B.d(B.java:2)\n\t... 3 more"
+            ]
+        );
+
+        let popped = joiner.pop_all();
+
+        assert_eq!(popped.len(), 1);
+        assert_eq!(popped[0].0, "c1");
+        assert_eq!(popped[0].1.message, "next");
+        assert!(joiner.pop_all().is_empty());
+    }
+
+    #[test]
+    fn test_push_python_traceback() {
+        let mut joiner = MultiLineJoiner::new(None);
+
+        let completed = joiner.push(
+            "c1",
+            vec![
+                new_json("a", "Traceback (most recent call last):"),
+                new_json("a", "  File \"/app/main.py\", line 3, in <module>"),
+                new_json("a", "    main()"),
+                new_json("a", "ValueError: bad"),
+                new_json("a", "done"),
+            ],
+        );
+
+        assert_eq!(
+            get_messages(completed),
+            vec!["Traceback (most recent call last):\n\tFile \"/app/main.py\", line 3, in <module>\n\tmain()\nValueError: bad"]
+        );
+    }
+
+    #[test]
+    fn test_push_go_goroutines() {
+        let mut joiner = MultiLineJoiner::new(None);
+
+        joiner.push(
+            "c1",
+            vec![
+                new_json("a", "goroutine 1 [running]:"),
+                new_json("a", "main.main()"),
+                new_json("a", "\t/app/main.go:5 +0x1d"),
+                new_json("a", ""),
+                new_json("a", "goroutine 2 [chan receive]:"),
+            ],
+        );
+
+        let popped = joiner.pop_all();
+
+        assert_eq!(popped.len(), 1);
+        assert_eq!(
+            popped[0].1.message,
+            "goroutine 1 [running]:\nmain.main()\n\t/app/main.go:5 +0x1d\n\ngoroutine 2 [chan receive]:"
+        );
+    }
+
+    #[test]
+    fn test_push_keeps_containers_apart() {
+        let mut joiner = MultiLineJoiner::new(None);
+
+        let completed = joiner.push(
+            "c1",
+            vec![
+                new_json("a", "Traceback (most recent call last):"),
+                new_json("b", "other"),
+                new_json("a", "  File \"/app/main.py\", line 3, in <module>"),
+            ],
+        );
+
+        assert!(completed.is_empty());
+
+        // the same pod in another context is another container
+        let completed = joiner.push("c2", vec![new_json("a", "ValueError: bad")]);
+
+        assert!(completed.is_empty());
+
+        let mut popped: Vec<String> = joiner
+            .pop_all()
+            .into_iter()
+            .map(|(context, json)| format!("{}/{}: {}", context, json.pod_name, json.message))
+            .collect();
+
+        popped.sort();
+
+        assert_eq!(
+            popped,
+            vec![
+                "c1/a: Traceback (most recent call last):\n\tFile \"/app/main.py\", line 3, in <module>",
+                "c1/b: other",
+                "c2/a: ValueError: bad",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_json_message() {
+        let mut joiner = MultiLineJoiner::new(None);
+
+        // a JSON message is never a part of a stack trace and is not held
+        let completed = joiner.push(
+            "c1",
+            vec![
+                new_json("a", "java.lang.IllegalStateException: bad"),
+                new_json("a", r#"{"message": "at the end"}"#),
+            ],
+        );
+
+        assert_eq!(
+            get_messages(completed),
+            vec![
+                "java.lang.IllegalStateException: bad",
+                r#"{"message": "at the end"}"#
+            ]
+        );
+        assert!(joiner.pop_all().is_empty());
+    }
+
+    #[test]
+    fn test_pop_ready() {
+        let mut joiner = MultiLineJoiner::new(None);
+
+        joiner.push("c1", vec![new_json("a", "first")]);
+
+        // without a timeout entries are held till the end of the streams
+        assert!(joiner.pop_ready().is_empty());
+
+        let mut joiner = MultiLineJoiner::new(Some(Duration::ZERO));
+
+        joiner.push("c1", vec![new_json("a", "first")]);
+
+        assert_eq!(joiner.pop_ready().len(), 1);
+        assert!(joiner.pop_ready().is_empty());
+
+        let mut joiner = MultiLineJoiner::new(Some(Duration::from_secs(60)));
+
+        joiner.push("c1", vec![new_json("a", "first")]);
+
+        assert!(joiner.pop_ready().is_empty());
+    }
+}
@@ -0,0 +1,46 @@
+use std::env::var;
+use std::fmt::Display;
+
+const ENV_PREFIX: &str = "DOH_";
+
+/// Where the effective value of an option comes from, in order of precedence
+/// from the lowest: built-in default, config file, environment, command line.
+pub enum OptionSource {
+    Default,
+    Config,
+    Profile(String),
+    Env(String),
+    CommandLine,
+}
+
+impl OptionSource {
+    /// Returns environment variable name of a long option, like "DOH_INCLUDE_CONTAINER"
+    /// for "--include-container".
+    pub fn get_env_name(arg: &str) -> String {
+        return format!(
+            "{}{}",
+            ENV_PREFIX,
+            arg.trim_start_matches('-').replace('-', "_").to_uppercase()
+        );
+    }
+
+    /// Returns value of the option's environment variable, empty ones are not set.
+    pub fn get_env_value(arg: &str) -> Option<String> {
+        return match var(Self::get_env_name(arg)) {
+            Ok(value) if !value.is_empty() => Some(value),
+            _ => None,
+        };
+    }
+}
+
+impl Display for OptionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            OptionSource::Default => f.write_str("default"),
+            OptionSource::Config => f.write_str("config"),
+            OptionSource::Profile(profile) => write!(f, "profile \"{}\"", profile),
+            OptionSource::Env(name) => write!(f, "env {}", name),
+            OptionSource::CommandLine => f.write_str("command line"),
+        };
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::{Error, Result};
+use serde_json::{json, Map, Value};
+
+use crate::stern_json::SternJSON;
+
+#[derive(PartialEq)]
+pub enum OutputFormat {
+    Text,      // "{context} {pod} {container} {timestamp}    {message}"
+    JsonLines, // one normalized JSON object per entry
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        return match s {
+            "text" => Ok(OutputFormat::Text),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            _ => Err(Error::msg(format!(
+                "\"{}\" no output format, use \"text\" or \"jsonl\"",
+                s
+            ))),
+        };
+    }
+}
+
+/// Builds the normalized JSON object printed for each entry with `--output jsonl`.
+pub fn entry_to_json_line(
+    context: &str,
+    json: &SternJSON,
+    message: &str,
+    shape: Option<&str>,
+    level: Option<&str>,
+    request_id: Option<&Value>,
+) -> String {
+    let timestamp = match json.datetime {
+        Some(datetime) => Value::String(datetime.to_rfc3339()),
+        None if !json.timestamp.is_empty() => Value::String(json.timestamp.to_string()),
+        None => Value::Null,
+    };
+
+    let fields = match &json.internal_json_message {
+        Some(internal_json_message) => Value::Object(Map::from_iter(
+            internal_json_message
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone())),
+        )),
+        None => Value::Null,
+    };
+
+    let line = json!({
+        "context": context,
+        "valid": json.is_valid,
+        "namespace": json.namespace,
+        "node": json.node_name,
+        "pod": json.pod_name,
+        "container": json.container_name,
+        "timestamp": timestamp,
+        "level": level,
+        "message": message,
+        "shape": shape,
+        "fields": fields,
+        "request_id": request_id,
+    });
+
+    return line.to_string();
+}
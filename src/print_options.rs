@@ -0,0 +1,95 @@
+use anyhow::Result;
+use regex::Regex;
+
+use crate::arg_parser::ArgParser;
+use crate::field_predicate::FieldPredicate;
+use crate::log_level::LogLevel;
+use crate::output_format::OutputFormat;
+use crate::recognizer::RecognizerRegistry;
+use crate::template::Template;
+
+/// Options used when printing parsed log entries, taken from the command line.
+pub struct PrintOptions {
+    pub skip_invalid_messages: bool,
+    pub blank_line_after_entry: bool,
+    pub include_container: Option<Vec<String>>,
+    pub quiet: bool,
+    pub fix_up_messages: bool,
+    pub pretty_print_objects: bool,
+    pub space_after_message: bool,
+    pub output_format: OutputFormat,
+    pub template: Template,
+    pub recognizers: RecognizerRegistry,
+    pub min_level: Option<LogLevel>,
+    pub grep: Vec<Regex>,                      // entry must match any of them
+    pub grep_v: Vec<Regex>,                    // entry must not match any of them
+    pub grep_raw: bool,                        // match also raw lines, not only messages
+    pub where_predicates: Vec<FieldPredicate>, // JSON message must match all of them
+}
+
+impl PrintOptions {
+    pub fn new(args: &ArgParser) -> Result<Self> {
+        let mut recognizers = RecognizerRegistry::new();
+
+        if let Some(arg_recognizers) = args.get_kv_arg_string("--recognizers", false, false) {
+            recognizers.load_from_file(&arg_recognizers)?;
+        }
+
+        let min_level = match args.get_kv_arg_string("--min-level", false, false) {
+            Some(arg_min_level) => Some(LogLevel::parse_arg(&arg_min_level)?),
+            None => None,
+        };
+
+        let mut grep = vec![];
+        let mut grep_v = vec![];
+
+        for iregex in args.get_kv_arg_values("--grep", false) {
+            grep.push(Regex::new(&iregex)?);
+        }
+
+        for iregex in args.get_kv_arg_values("--grep-v", false) {
+            grep_v.push(Regex::new(&iregex)?);
+        }
+
+        let mut where_predicates = vec![];
+
+        for ipredicate in args.get_kv_arg_values("--where", false) {
+            where_predicates.push(FieldPredicate::parse(&ipredicate)?);
+        }
+
+        return Ok(PrintOptions {
+            skip_invalid_messages: args
+                .get_bool_kv_arg("--skip-invalid-messages", false)
+                .unwrap(),
+            blank_line_after_entry: args
+                .get_bool_kv_arg("--blank-line-after-entry", false)
+                .unwrap(),
+            include_container: args.get_kv_arg_array_string(
+                "--include-container",
+                ",",
+                false,
+                false,
+            ),
+            quiet: args.args.contains(&"--quiet".into()),
+            fix_up_messages: args.get_bool_kv_arg("--fix-up-messages", false).unwrap(),
+            pretty_print_objects: args
+                .get_bool_kv_arg("--pretty-print-objects", false)
+                .unwrap(),
+            space_after_message: args
+                .get_bool_kv_arg("--space-after-message", false)
+                .unwrap(),
+            output_format: OutputFormat::parse(
+                &args.get_kv_arg_string("--output", false, false).unwrap(),
+            )?,
+            template: Template::parse(
+                &args.get_kv_arg_string("--template", false, false).unwrap(),
+            )?,
+            recognizers,
+            min_level,
+            grep,
+            grep_v,
+            grep_raw: args.get_bool_kv_arg("--grep-raw", false).unwrap(),
+            where_predicates,
+        });
+    }
+}
@@ -0,0 +1,324 @@
+use anyhow::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+
+use crate::json_utils::get_value_by_path;
+use crate::template::Template;
+
+/// How a recognized JSON message is printed.
+pub enum Renderer {
+    Message,            // "{message_key}" value
+    ExcInfo,            // "{exc_info_key}" value line, then "{message_key}" value line
+    Proxy,              // Envoy-like access log line
+    Template(Template), // message built from a template over the JSON message fields
+    Json,               // whole JSON message as is
+}
+
+/// Maps a set of keys which must exist in a JSON message to a renderer.
+pub struct Recognizer {
+    pub name: String,
+    pub keys: Vec<String>,
+    pub renderer: Renderer,
+    pub message_key: String,
+    pub exc_info_key: String,
+    pub request_id_key: String,
+}
+
+impl Recognizer {
+    fn new(name: &str, keys: &[&str], renderer: Renderer) -> Self {
+        return Recognizer {
+            name: name.to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            renderer,
+            message_key: "message".to_string(),
+            exc_info_key: "exc_info".to_string(),
+            request_id_key: "request_id".to_string(),
+        };
+    }
+
+    fn from_json(value: &Value) -> Result<Self> {
+        let name = value["name"].as_str().unwrap_or("unnamed");
+        let keys = match value["keys"].as_array() {
+            Some(keys) if !keys.is_empty() => keys,
+            _ => {
+                return Err(Error::msg(format!(
+                    "recognizer \"{}\" has no \"keys\"",
+                    name
+                )))
+            }
+        };
+
+        let mut recognizer = Recognizer::new(name, &[], Renderer::Json);
+
+        for ikey in keys {
+            match ikey.as_str() {
+                Some(ikey) => recognizer.keys.push(ikey.to_string()),
+                None => {
+                    return Err(Error::msg(format!(
+                        "recognizer \"{}\" has non-string key {}",
+                        name, ikey
+                    )))
+                }
+            }
+        }
+
+        recognizer.renderer = match value["renderer"].as_str().unwrap_or("message") {
+            "message" => Renderer::Message,
+            "exc_info" => Renderer::ExcInfo,
+            "proxy" => Renderer::Proxy,
+            "json" => Renderer::Json,
+            "template" => match value["template"].as_str() {
+                Some(template) => Renderer::Template(Template::parse(template)?),
+                None => {
+                    return Err(Error::msg(format!(
+                        "recognizer \"{}\" has no \"template\"",
+                        name
+                    )))
+                }
+            },
+            renderer => {
+                return Err(Error::msg(format!(
+                    "recognizer \"{}\" has unknown renderer \"{}\"",
+                    name, renderer
+                )))
+            }
+        };
+
+        if let Some(message_key) = value["message_key"].as_str() {
+            recognizer.message_key = message_key.to_string();
+        }
+
+        if let Some(exc_info_key) = value["exc_info_key"].as_str() {
+            recognizer.exc_info_key = exc_info_key.to_string();
+        }
+
+        if let Some(request_id_key) = value["request_id_key"].as_str() {
+            recognizer.request_id_key = request_id_key.to_string();
+        }
+
+        return Ok(recognizer);
+    }
+
+    pub fn is_match(&self, internal_json_message: &HashMap<String, Value>) -> bool {
+        if !self
+            .keys
+            .iter()
+            .all(|key| get_value_by_path(internal_json_message, key).is_some())
+        {
+            return false;
+        }
+
+        // e.g. "exc_info": true or null, the next recognizer prints such a message
+        if let Renderer::ExcInfo = self.renderer {
+            return [&self.exc_info_key, &self.message_key].iter().all(|key| {
+                matches!(
+                    get_value_by_path(internal_json_message, key),
+                    Some(Value::String(_))
+                )
+            });
+        }
+
+        return true;
+    }
+}
+
+/// Recognizers of JSON message shapes, checked in order; the ones loaded
+/// from a file go before the built-in ones.
+pub struct RecognizerRegistry {
+    recognizers: Vec<Recognizer>,
+}
+
+impl RecognizerRegistry {
+    pub fn new() -> Self {
+        return RecognizerRegistry {
+            recognizers: vec![
+                Recognizer::new("exc_info", &["exc_info", "message"], Renderer::ExcInfo),
+                Recognizer::new("message", &["message"], Renderer::Message),
+                Recognizer::new(
+                    "proxy",
+                    &[
+                        "downstream_local_address",
+                        "method",
+                        "path",
+                        "protocol",
+                        "response_code",
+                        "bytes_sent",
+                        "bytes_received",
+                        "duration",
+                        "upstream_service_time",
+                    ],
+                    Renderer::Proxy,
+                ),
+            ],
+        };
+    }
+
+    /// Loads recognizers from a JSON file like:
+    ///
+    /// ```json
+    /// {"recognizers": [{"name": "zap", "keys": ["msg", "level", "caller"], "renderer": "message", "message_key": "msg"}]}
+    /// ```
+    pub fn load_from_file(&mut self, pathname: &str) -> Result<()> {
+        let parsed: Value = match serde_json::from_str(&read_to_string(pathname)?) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "cannot parse recognizers file \"{}\" ({})",
+                    pathname, err
+                )))
+            }
+        };
+
+        let mut loaded = vec![];
+
+        match parsed["recognizers"].as_array() {
+            Some(recognizers) => {
+                for irecognizer in recognizers {
+                    loaded.push(Recognizer::from_json(irecognizer)?);
+                }
+            }
+            None => {
+                return Err(Error::msg(format!(
+                    "recognizers file \"{}\" has no \"recognizers\" array",
+                    pathname
+                )))
+            }
+        }
+
+        loaded.append(&mut self.recognizers);
+        self.recognizers = loaded;
+
+        return Ok(());
+    }
+
+    pub fn find(&self, internal_json_message: &HashMap<String, Value>) -> Option<&Recognizer> {
+        return self
+            .recognizers
+            .iter()
+            .find(|recognizer| recognizer.is_match(internal_json_message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_utils::json_to_hashmap;
+    use serde_json::json;
+    use std::fs::{remove_file, write};
+
+    fn find_name(registry: &RecognizerRegistry, message: &str) -> Option<String> {
+        return registry
+            .find(&json_to_hashmap(message).unwrap())
+            .map(|recognizer| recognizer.name.to_string());
+    }
+
+    #[test]
+    fn test_find() {
+        let registry = RecognizerRegistry::new();
+
+        assert_eq!(
+            find_name(&registry, r#"{"message": "m", "exc_info": "trace"}"#),
+            Some("exc_info".into())
+        );
+        assert_eq!(
+            find_name(&registry, r#"{"message": "m"}"#),
+            Some("message".into())
+        );
+        assert_eq!(
+            find_name(
+                &registry,
+                r#"{"downstream_local_address": "a", "method": "GET", "path": "/", "protocol": "HTTP/1.1", "response_code": 200, "bytes_sent": 1, "bytes_received": 0, "duration": 5, "upstream_service_time": "4"}"#
+            ),
+            Some("proxy".into())
+        );
+        assert_eq!(find_name(&registry, r#"{"msg": "m"}"#), None);
+    }
+
+    #[test]
+    fn test_find_exc_info_not_string() {
+        let registry = RecognizerRegistry::new();
+
+        // falls through to the next recognizer instead of printing the whole JSON
+        assert_eq!(
+            find_name(&registry, r#"{"message": "m", "exc_info": true}"#),
+            Some("message".into())
+        );
+        assert_eq!(
+            find_name(&registry, r#"{"message": "m", "exc_info": null}"#),
+            Some("message".into())
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let recognizer = Recognizer::from_json(&json!({
+            "name": "zap",
+            "keys": ["msg", "caller"],
+            "renderer": "exc_info",
+            "message_key": "msg",
+            "exc_info_key": "stacktrace",
+            "request_id_key": "trace_id",
+        }))
+        .unwrap();
+
+        assert_eq!(recognizer.name, "zap");
+        assert_eq!(recognizer.keys, vec!["msg", "caller"]);
+        assert!(matches!(recognizer.renderer, Renderer::ExcInfo));
+        assert_eq!(recognizer.message_key, "msg");
+        assert_eq!(recognizer.exc_info_key, "stacktrace");
+        assert_eq!(recognizer.request_id_key, "trace_id");
+
+        let recognizer = Recognizer::from_json(&json!({"keys": ["a.b"]})).unwrap();
+
+        assert_eq!(recognizer.name, "unnamed");
+        assert!(matches!(recognizer.renderer, Renderer::Message));
+        assert!(recognizer.is_match(&json_to_hashmap(r#"{"a": {"b": 1}}"#).unwrap()));
+        assert!(!recognizer.is_match(&json_to_hashmap(r#"{"a": 1}"#).unwrap()));
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        assert!(Recognizer::from_json(&json!({"name": "x"})).is_err());
+        assert!(Recognizer::from_json(&json!({"keys": []})).is_err());
+        assert!(Recognizer::from_json(&json!({"keys": [1]})).is_err());
+        assert!(Recognizer::from_json(&json!({"keys": ["a"], "renderer": "xml"})).is_err());
+        assert!(Recognizer::from_json(&json!({"keys": ["a"], "renderer": "template"})).is_err());
+        assert!(Recognizer::from_json(
+            &json!({"keys": ["a"], "renderer": "template", "template": "{a"})
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_load_from_file() {
+        let pathname =
+            std::env::temp_dir().join(format!("doh-recognizers-{}.json", std::process::id()));
+        let pathname = pathname.to_str().unwrap();
+
+        write(
+            pathname,
+            r#"{"recognizers": [{"name": "zap", "keys": ["msg", "message"], "message_key": "msg"}]}"#,
+        )
+        .unwrap();
+
+        let mut registry = RecognizerRegistry::new();
+        let result = registry.load_from_file(pathname);
+
+        remove_file(pathname).unwrap();
+        result.unwrap();
+
+        // loaded recognizers go before the built-in ones
+        assert_eq!(
+            find_name(&registry, r#"{"msg": "m", "message": "m"}"#),
+            Some("zap".into())
+        );
+        assert_eq!(
+            find_name(&registry, r#"{"message": "m"}"#),
+            Some("message".into())
+        );
+        assert!(registry
+            .load_from_file("/nonexistent/recognizers.json")
+            .is_err());
+    }
+}
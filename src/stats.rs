@@ -1,7 +1,69 @@
+use anyhow::{Error, Result};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::process::ExitStatus;
+use std::time::Instant;
+
+use crate::exception_summary::ExceptionSummary;
+use crate::log_level::LogLevel;
+use crate::stern_json::SternJSON;
+
+// number of the last stderr lines kept per context
+const MAX_STDERR_LINES: usize = 5;
+
+#[derive(PartialEq)]
+pub enum StatsFormat {
+    Text,  // global counters only
+    Table, // global counters and a table broken down by context, namespace, pod and container
+    Json,  // everything as one JSON object
+}
+
+impl StatsFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        return match s {
+            "text" => Ok(StatsFormat::Text),
+            "table" => Ok(StatsFormat::Table),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(Error::msg(format!(
+                "\"{}\" no stats format, use \"text\", \"table\" or \"json\"",
+                s
+            ))),
+        };
+    }
+}
+
+/// Counters of a single (context, namespace, pod, container), invalid
+/// lines have empty namespace, pod and container.
+#[derive(Default)]
+pub struct EntryStats {
+    pub total_logs: u128,
+    pub filtered_out_logs: u128,
+    pub printed_logs: u128,
+    pub invalid_logs: u128,
+    pub bytes: u128,
+    pub level_logs: BTreeMap<LogLevel, u128>,
+}
+
+/// Streams of a context which exited unsuccessfully and what they printed to stderr.
+#[derive(Default)]
+pub struct ContextFailures {
+    pub failed_streams: u128,
+    pub exit_statuses: Vec<String>,
+    pub stderr_lines: Vec<String>, // the last ones only
+}
+
 pub struct Stats {
     pub total_logs: u128,
     pub filtered_out_logs: u128,
     pub printed_logs: u128,
+    pub invalid_logs: u128,
+    pub bytes: u128,                          // bytes of raw lines
+    pub replaced_chars: u128,                 // invalid UTF-8 sequences replaced in the output
+    pub level_logs: BTreeMap<LogLevel, u128>, // total logs by extracted level
+    pub entries: BTreeMap<(String, String, String, String), EntryStats>,
+    pub exceptions: ExceptionSummary,
+    pub failures: BTreeMap<String, ContextFailures>,
+    started: Instant,
 }
 
 impl Stats {
@@ -10,6 +72,235 @@ impl Stats {
             total_logs: 0,
             filtered_out_logs: 0,
             printed_logs: 0,
+            invalid_logs: 0,
+            bytes: 0,
+            replaced_chars: 0,
+            level_logs: BTreeMap::new(),
+            entries: BTreeMap::new(),
+            exceptions: ExceptionSummary::new(),
+            failures: BTreeMap::new(),
+            started: Instant::now(),
         };
     }
+
+    pub fn count_total(&mut self, context: &str, json: &SternJSON) {
+        let bytes = json.raw.len() as u128;
+
+        self.total_logs += 1;
+        self.bytes += bytes;
+
+        if !json.is_valid {
+            self.invalid_logs += 1;
+        }
+
+        if let Some(level) = json.level {
+            *self.level_logs.entry(level).or_default() += 1;
+        }
+
+        let entry_stats = self.get_entry_stats(context, json);
+
+        entry_stats.total_logs += 1;
+        entry_stats.bytes += bytes;
+
+        if !json.is_valid {
+            entry_stats.invalid_logs += 1;
+        }
+
+        if let Some(level) = json.level {
+            *entry_stats.level_logs.entry(level).or_default() += 1;
+        }
+    }
+
+    pub fn count_filtered_out(&mut self, context: &str, json: &SternJSON) {
+        self.filtered_out_logs += 1;
+        self.get_entry_stats(context, json).filtered_out_logs += 1;
+    }
+
+    pub fn count_printed(&mut self, context: &str, json: &SternJSON) {
+        self.printed_logs += 1;
+        self.get_entry_stats(context, json).printed_logs += 1;
+    }
+
+    pub fn count_replaced_chars(&mut self, count: usize) {
+        self.replaced_chars += count as u128;
+    }
+
+    pub fn count_stderr(&mut self, context: &str, line: &str) {
+        let stderr_lines = &mut self
+            .failures
+            .entry(context.to_string())
+            .or_default()
+            .stderr_lines;
+
+        stderr_lines.push(line.to_string());
+        stderr_lines.drain(..stderr_lines.len().saturating_sub(MAX_STDERR_LINES));
+    }
+
+    pub fn count_exit(&mut self, context: &str, exit_status: &ExitStatus) {
+        if exit_status.success() {
+            return;
+        }
+
+        let failures = self.failures.entry(context.to_string()).or_default();
+
+        failures.failed_streams += 1;
+        failures.exit_statuses.push(exit_status.to_string());
+    }
+
+    pub fn get_failed_contexts(&self) -> Vec<&str> {
+        return self
+            .failures
+            .iter()
+            .filter(|(_, failures)| failures.failed_streams > 0)
+            .map(|(context, _)| context.as_str())
+            .collect();
+    }
+
+    pub fn get_lines_per_second(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+
+        return self.total_logs as f64 / elapsed;
+    }
+
+    /// Returns breakdown table lines, header first.
+    pub fn to_table(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:<20}  {:<20}  {:<40}  {:<20}  {:>8}  {:>8}  {:>8}  {:>8}  {:>10}  {}",
+            "CONTEXT",
+            "NAMESPACE",
+            "POD",
+            "CONTAINER",
+            "TOTAL",
+            "FILTERED",
+            "PRINTED",
+            "INVALID",
+            "BYTES",
+            "LEVELS"
+        )];
+
+        for ((context, namespace, pod, container), entry_stats) in &self.entries {
+            lines.push(
+                format!(
+                    "{:<20}  {:<20}  {:<40}  {:<20}  {:>8}  {:>8}  {:>8}  {:>8}  {:>10}  {}",
+                    context,
+                    namespace,
+                    pod,
+                    container,
+                    entry_stats.total_logs,
+                    entry_stats.filtered_out_logs,
+                    entry_stats.printed_logs,
+                    entry_stats.invalid_logs,
+                    entry_stats.bytes,
+                    Self::format_levels(&entry_stats.level_logs, ":", " ")
+                )
+                .trim_end()
+                .to_string(),
+            );
+        }
+
+        return lines;
+    }
+
+    pub fn to_json(&self) -> Value {
+        let breakdown: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|((context, namespace, pod, container), entry_stats)| {
+                json!({
+                    "context": context,
+                    "namespace": namespace,
+                    "pod": pod,
+                    "container": container,
+                    "total_logs": entry_stats.total_logs as u64,
+                    "filtered_out_logs": entry_stats.filtered_out_logs as u64,
+                    "printed_logs": entry_stats.printed_logs as u64,
+                    "invalid_logs": entry_stats.invalid_logs as u64,
+                    "bytes": entry_stats.bytes as u64,
+                    "levels": Self::levels_to_json(&entry_stats.level_logs),
+                })
+            })
+            .collect();
+
+        let exceptions: Vec<Value> = self
+            .exceptions
+            .get_sorted_groups()
+            .iter()
+            .map(|group| {
+                json!({
+                    "type": group.exception_type,
+                    "count": group.count as u64,
+                    "first_seen": group.first_seen.map(|datetime| datetime.to_rfc3339()),
+                    "last_seen": group.last_seen.map(|datetime| datetime.to_rfc3339()),
+                    "contexts": group.contexts,
+                    "sample_message": group.sample_message,
+                })
+            })
+            .collect();
+
+        let failures: Vec<Value> = self
+            .failures
+            .iter()
+            .map(|(context, failures)| {
+                json!({
+                    "context": context,
+                    "failed_streams": failures.failed_streams as u64,
+                    "exit_statuses": failures.exit_statuses,
+                    "stderr_lines": failures.stderr_lines,
+                })
+            })
+            .collect();
+
+        return json!({
+            "total_logs": self.total_logs as u64,
+            "filtered_out_logs": self.filtered_out_logs as u64,
+            "printed_logs": self.printed_logs as u64,
+            "invalid_logs": self.invalid_logs as u64,
+            "bytes": self.bytes as u64,
+            "replaced_chars": self.replaced_chars as u64,
+            "elapsed_seconds": self.started.elapsed().as_secs_f64(),
+            "lines_per_second": self.get_lines_per_second(),
+            "levels": Self::levels_to_json(&self.level_logs),
+            "breakdown": breakdown,
+            "exceptions": exceptions,
+            "failures": failures,
+        });
+    }
+
+    /// Formats level counts from the most severe, like "error: 3, warn: 1".
+    pub fn format_levels(
+        level_logs: &BTreeMap<LogLevel, u128>,
+        separator: &str,
+        joiner: &str,
+    ) -> String {
+        return level_logs
+            .iter()
+            .rev()
+            .map(|(level, count)| format!("{}{}{}", level, separator, count))
+            .collect::<Vec<String>>()
+            .join(joiner);
+    }
+
+    fn levels_to_json(level_logs: &BTreeMap<LogLevel, u128>) -> Value {
+        return Value::Object(Map::from_iter(
+            level_logs
+                .iter()
+                .map(|(level, count)| (level.to_string(), json!(*count as u64))),
+        ));
+    }
+
+    fn get_entry_stats(&mut self, context: &str, json: &SternJSON) -> &mut EntryStats {
+        return self
+            .entries
+            .entry((
+                context.to_string(),
+                json.namespace.to_string(),
+                json.pod_name.to_string(),
+                json.container_name.to_string(),
+            ))
+            .or_default();
+    }
 }
@@ -1,10 +1,12 @@
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeDelta, Utc};
 use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::log_level::LogLevel;
 use crate::stern_json_regex::SternJSONRegEx;
 use crate::{json_utils::json_to_hashmap, string_utils::tokenize_by};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SternJSON {
     // same names as exists in the json
     pub timestamp: String,      // timestamp extracted from "message"
@@ -16,6 +18,9 @@ pub struct SternJSON {
 
     pub is_valid: bool,
     pub raw: String,
+    pub datetime: Option<DateTime<Utc>>, // parsed "timestamp"
+    pub level: Option<LogLevel>,         // extracted from "message"
+    pub is_indented: bool,               // "message" after the timestamp started with whitespace
     pub internal_json_message: Option<HashMap<String, Value>>, // parsed json from "message"
 }
 
@@ -24,17 +29,7 @@ impl SternJSON {
         let mut parsed = vec![];
 
         for iline in tokenize_by(lines, "\n".into(), -1, true, true) {
-            let mut json = SternJSON {
-                timestamp: "".to_string(),
-                message: "".to_string(),
-                node_name: "".to_string(),
-                namespace: "".to_string(),
-                pod_name: "".to_string(),
-                container_name: "".to_string(),
-                is_valid: false,
-                raw: iline.to_string(),
-                internal_json_message: None,
-            };
+            let mut json = Self::new_invalid(&iline);
 
             if iline.starts_with("{") && iline.ends_with("}") {
                 match json_to_hashmap(&iline) {
@@ -50,6 +45,49 @@ impl SternJSON {
         return parsed;
     }
 
+    /// Creates a valid entry from already known fields, for log sources
+    /// which do not produce stern's JSON envelope.
+    pub fn from_fields(
+        raw: &str,
+        message: &str,
+        node_name: &str,
+        namespace: &str,
+        pod_name: &str,
+        container_name: &str,
+        regex: Option<&SternJSONRegEx>,
+    ) -> Self {
+        let mut json = Self::new_invalid(raw);
+
+        json.message = message.trim().to_string();
+        json.node_name = node_name.trim().to_string();
+        json.namespace = namespace.trim().to_string();
+        json.pod_name = pod_name.trim().to_string();
+        json.container_name = container_name.trim().to_string();
+
+        json.is_valid = true;
+
+        Self::fill_internal_json_message(&mut json, regex);
+
+        return json;
+    }
+
+    fn new_invalid(raw: &str) -> Self {
+        return SternJSON {
+            timestamp: "".to_string(),
+            datetime: None,
+            level: None,
+            is_indented: false,
+            message: "".to_string(),
+            node_name: "".to_string(),
+            namespace: "".to_string(),
+            pod_name: "".to_string(),
+            container_name: "".to_string(),
+            is_valid: false,
+            raw: raw.to_string(),
+            internal_json_message: None,
+        };
+    }
+
     fn fill_from_hashmap(
         json: &mut SternJSON,
         hashmap: HashMap<String, Value>,
@@ -94,21 +132,85 @@ impl SternJSON {
                 Self::extract_ts_message_internal_message(json, regex);
             }
         }
+
+        json.level = Self::extract_level(json, regex);
+    }
+
+    fn extract_level(json: &SternJSON, regex: Option<&SternJSONRegEx>) -> Option<LogLevel> {
+        if let Some(internal_json_message) = &json.internal_json_message {
+            if let Some(level) = LogLevel::from_json_message(internal_json_message) {
+                return Some(level);
+            }
+        }
+
+        let regex = regex?;
+
+        if let Some(parsed) = regex.level_klog.captures(&json.message) {
+            return LogLevel::from_klog_letter(&parsed["level"]);
+        }
+
+        for iregex in [
+            &regex.level_tag,
+            &regex.level_after_timestamp,
+            &regex.level_prefix,
+            &regex.level_logfmt,
+        ] {
+            if let Some(level) = iregex
+                .captures(&json.message)
+                .and_then(|parsed| LogLevel::parse(&parsed["level"]))
+            {
+                return Some(level);
+            }
+        }
+
+        return None;
     }
 
     fn extract_ts_message_internal_message(json: &mut SternJSON, regex: &SternJSONRegEx) {
         if let Some(parsed) = regex.full_timestamp_and_message.captures(&json.message) {
             json.timestamp = parsed["full_timestamp"].to_string().trim().to_string();
+            json.is_indented = parsed["message"].starts_with(char::is_whitespace);
             json.message = parsed["message"].to_string().trim().to_string();
         } else if let Some(parsed) = regex.short_timestamp_and_message.captures(&json.message) {
             json.timestamp = parsed["short_timestamp"].to_string().trim().to_string();
+            json.is_indented = parsed["message"].starts_with(char::is_whitespace);
             json.message = parsed["message"].to_string().trim().to_string();
         }
 
+        json.datetime = Self::parse_timestamp(&json.timestamp);
+
         if !json.message.is_empty() {
             if let Ok(parsed_json) = json_to_hashmap(&json.message) {
                 json.internal_json_message = Some(parsed_json);
             }
         }
     }
+
+    fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+        if timestamp.is_empty() {
+            return None;
+        }
+
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(timestamp) {
+            return Some(datetime.with_timezone(&Utc));
+        }
+
+        // short timestamp has no year, stern prints it in UTC (--timezone UTC)
+        let now = Utc::now();
+
+        for year in [now.year(), now.year() - 1] {
+            if let Ok(naive) = NaiveDateTime::parse_from_str(
+                &format!("{}-{}", year, timestamp),
+                "%Y-%m-%d %H:%M:%S",
+            ) {
+                let datetime = naive.and_utc();
+
+                if datetime <= now + TimeDelta::days(1) {
+                    return Some(datetime);
+                }
+            }
+        }
+
+        return None;
+    }
 }
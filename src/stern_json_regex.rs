@@ -1,12 +1,26 @@
 use regex::Regex;
 
-const FULL_TIMESTAMP_AND_MESSAGE: &str = r"^(?P<full_timestamp>(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})T(?P<hour>\d{2}):(?P<minute>\d{2}):(?P<second>\d{2})(?:\.(?P<nanoseconds>\d{1,9}))?(?P<tz_sign>[+-])(?P<tz_hour>\d{2}):(?P<tz_minute>\d{2})) ?(?P<message>.*)$";
+const FULL_TIMESTAMP_AND_MESSAGE: &str = r"^(?P<full_timestamp>(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})T(?P<hour>\d{2}):(?P<minute>\d{2}):(?P<second>\d{2})(?:\.(?P<nanoseconds>\d{1,9}))?(?:(?P<tz_sign>[+-])(?P<tz_hour>\d{2}):(?P<tz_minute>\d{2})|Z)) ?(?P<message>.*)$";
 const SHORT_TIMESTAMP_AND_MESSAGE: &str =
     r"^(?P<short_timestamp>\d{2}-\d{2} \d{2}:\d{2}:\d{2}) ?(?P<message>.*)$";
 
+// same shapes as START_TIMESTAMP_1 and START_TIMESTAMP_2 in message_regex.rs
+const LEVEL_TAG: &str = r"^\d{14}\.?\d{0,3}\[(?P<level>[A-Z]+)\]";
+const LEVEL_AFTER_TIMESTAMP: &str =
+    r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}[.,]?\d{0,3} (?P<level>[A-Z]+)\s";
+const LEVEL_KLOG: &str = r"^(?P<level>[IWEF])\d{4} \d{2}:\d{2}:\d{2}\.\d+\s+\d+ ";
+const LEVEL_PREFIX: &str =
+    r"^\[?(?P<level>TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|FATAL|CRITICAL|PANIC)\]?[:\s]";
+const LEVEL_LOGFMT: &str = r#"(?:^|\s)(?:level|lvl)="?(?P<level>[A-Za-z]+)"#;
+
 pub(crate) struct SternJSONRegEx {
-    pub(crate) full_timestamp_and_message: Regex, // 2021-08-26T21:52:09+02:00 message
+    pub(crate) full_timestamp_and_message: Regex, // 2021-08-26T21:52:09+02:00 or ...09.123456789Z message
     pub(crate) short_timestamp_and_message: Regex, // 08-26 22:08:51 message
+    pub(crate) level_tag: Regex,                  // 20250902140313.122[ERR][...
+    pub(crate) level_after_timestamp: Regex,      // 2025-09-02 12:58:52.123 INFO ...
+    pub(crate) level_klog: Regex,                 // E0902 12:58:52.123456       1 file.go:61] ...
+    pub(crate) level_prefix: Regex,               // [ERROR] ... or WARNING: ...
+    pub(crate) level_logfmt: Regex,               // ... level=warn ...
 }
 
 impl SternJSONRegEx {
@@ -14,6 +28,11 @@ impl SternJSONRegEx {
         return SternJSONRegEx {
             full_timestamp_and_message: Regex::new(FULL_TIMESTAMP_AND_MESSAGE).unwrap(),
             short_timestamp_and_message: Regex::new(SHORT_TIMESTAMP_AND_MESSAGE).unwrap(),
+            level_tag: Regex::new(LEVEL_TAG).unwrap(),
+            level_after_timestamp: Regex::new(LEVEL_AFTER_TIMESTAMP).unwrap(),
+            level_klog: Regex::new(LEVEL_KLOG).unwrap(),
+            level_prefix: Regex::new(LEVEL_PREFIX).unwrap(),
+            level_logfmt: Regex::new(LEVEL_LOGFMT).unwrap(),
         };
     }
 }
@@ -0,0 +1,357 @@
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    process::ExitStatus,
+    time::{Duration, Instant},
+};
+
+use crate::command_streamer::CommandStreamer;
+use crate::stern_json::SternJSON;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const SPAWN_NEW_INTERVAL: Duration = Duration::from_secs(30);
+
+// namespace, pod and container of an entry
+type SourceKey = (String, String, String);
+
+#[derive(Default)]
+struct SeenSource {
+    last_datetime: Option<DateTime<Utc>>,
+    last_raws: Vec<String>,             // raw lines seen at last_datetime
+    dedup_until: Option<DateTime<Utc>>, // last_datetime at the restart, till it is passed
+}
+
+#[derive(Default)]
+struct SupervisedStream {
+    started: Option<Instant>,
+    running_since: Option<Instant>,
+    last_datetime: Option<DateTime<Utc>>,
+    sources: HashMap<SourceKey, SeenSource>,
+    attempts: u32,
+    restart_at: Option<Instant>,
+    finished: bool,
+}
+
+/// Watches streamers of a `MultiCommandStreamer` in follow mode, by their index, and schedules
+/// a restart of each one which exited, with exponential backoff.
+///
+/// The restarted command gets `--since` covering the time from the last seen entry. One stream
+/// mixes many pods and containers, so each of them is deduplicated on its own: entries up to its
+/// last entry seen before the restart are dropped, newer ones and ones of new containers are kept.
+pub struct StreamSupervisor {
+    streams: HashMap<usize, SupervisedStream>,
+    spawn_new_at: Instant,
+}
+
+impl StreamSupervisor {
+    pub fn new() -> Self {
+        return StreamSupervisor {
+            streams: HashMap::new(),
+            spawn_new_at: Instant::now() + SPAWN_NEW_INTERVAL,
+        };
+    }
+
+    /// Remembers the last entry of each container of the stream, returns entries
+    /// which were not seen yet.
+    pub fn observe(&mut self, index: usize, parsed_lines: Vec<SternJSON>) -> Vec<SternJSON> {
+        let stream = self.get_stream(index);
+        let mut observed = vec![];
+
+        for json in parsed_lines {
+            let datetime = match json.datetime {
+                Some(datetime) => datetime,
+                None => {
+                    observed.push(json);
+                    continue;
+                }
+            };
+            let source = stream
+                .sources
+                .entry((
+                    json.namespace.to_string(),
+                    json.pod_name.to_string(),
+                    json.container_name.to_string(),
+                ))
+                .or_default();
+
+            if let Some(dedup_until) = source.dedup_until {
+                if datetime < dedup_until
+                    || (datetime == dedup_until && source.last_raws.contains(&json.raw))
+                {
+                    continue;
+                }
+
+                if datetime > dedup_until {
+                    source.dedup_until = None;
+                }
+            }
+
+            match source.last_datetime {
+                Some(last_datetime) if datetime < last_datetime => {}
+                Some(last_datetime) if datetime == last_datetime => {
+                    source.last_raws.push(json.raw.to_string());
+                }
+                _ => {
+                    source.last_datetime = Some(datetime);
+                    source.last_raws = vec![json.raw.to_string()];
+                }
+            }
+
+            if stream
+                .last_datetime
+                .is_none_or(|last_datetime| datetime > last_datetime)
+            {
+                stream.last_datetime = Some(datetime);
+            }
+
+            observed.push(json);
+        }
+
+        return observed;
+    }
+
+    /// Schedules a restart of the stream if its command exited, returns the exit status
+    /// and the backoff only once per exit.
+    pub fn check_exited(
+        &mut self,
+        index: usize,
+        streamer: &mut CommandStreamer,
+    ) -> Option<(ExitStatus, Duration)> {
+        let stream = self.get_stream(index);
+
+        if stream.finished
+            || stream.restart_at.is_some()
+            || !streamer.is_eof()
+            || streamer.has_data_in_buffers()
+        {
+            return None;
+        }
+
+        let exit_status = streamer.try_exit_status()?;
+
+        return Some((exit_status, self.schedule_restart(index)));
+    }
+
+    pub fn schedule_restart(&mut self, index: usize) -> Duration {
+        let stream = self.get_stream(index);
+
+        // a stream which ran long enough did not fail right after its restart
+        if stream
+            .running_since
+            .is_some_and(|running_since| running_since.elapsed() >= MAX_BACKOFF)
+        {
+            stream.attempts = 0;
+        }
+
+        let backoff = INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(stream.attempts))
+            .min(MAX_BACKOFF);
+
+        stream.attempts += 1;
+        stream.restart_at = Some(Instant::now() + backoff);
+
+        return backoff;
+    }
+
+    /// Returns `--since` value for the restart of the stream once its backoff has passed.
+    pub fn pop_restart_since(&mut self, index: usize) -> Option<String> {
+        let stream = self.get_stream(index);
+
+        if stream
+            .restart_at
+            .is_none_or(|restart_at| Instant::now() < restart_at)
+        {
+            return None;
+        }
+
+        let since = match (stream.last_datetime, stream.started) {
+            (Some(last_datetime), _) => (Utc::now() - last_datetime).to_std().unwrap_or_default(),
+            (None, Some(started)) => started.elapsed(),
+            (None, None) => Duration::ZERO,
+        };
+
+        stream.restart_at = None;
+        stream.running_since = Some(Instant::now());
+
+        for isource in stream.sources.values_mut() {
+            isource.dedup_until = isource.last_datetime;
+        }
+
+        // one second more, entries seen already are dropped anyway
+        return Some(format!("{}s", since.as_secs() + 1));
+    }
+
+    /// Stops watching the stream, e.g. once the pod it streamed is gone.
+    pub fn finish(&mut self, index: usize) {
+        let stream = self.get_stream(index);
+
+        stream.finished = true;
+        stream.restart_at = None;
+
+        // e.g. a replacement of the pod
+        self.spawn_new_at = Instant::now();
+    }
+
+    /// Tells periodically, and right after a stream was finished, to look for new
+    /// things to stream (`LogSource::spawn_new`).
+    pub fn pop_spawn_new(&mut self) -> bool {
+        if Instant::now() < self.spawn_new_at {
+            return false;
+        }
+
+        self.spawn_new_at = Instant::now() + SPAWN_NEW_INTERVAL;
+
+        return true;
+    }
+
+    fn get_stream(&mut self, index: usize) -> &mut SupervisedStream {
+        return self
+            .streams
+            .entry(index)
+            .or_insert_with(|| SupervisedStream {
+                started: Some(Instant::now()),
+                running_since: Some(Instant::now()),
+                ..Default::default()
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn new_json(pod_name: &str, second: u32, message: &str) -> SternJSON {
+        let raw = format!("{} {} {}", pod_name, second, message);
+        let mut json = SternJSON::from_fields(&raw, message, "node", "ns", pod_name, "app", None);
+
+        json.datetime = Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap());
+
+        return json;
+    }
+
+    fn get_messages(observed: Vec<SternJSON>) -> Vec<String> {
+        return observed.into_iter().map(|ijson| ijson.message).collect();
+    }
+
+    fn restart(supervisor: &mut StreamSupervisor, index: usize) {
+        supervisor.get_stream(index).restart_at = Some(Instant::now());
+        assert!(supervisor.pop_restart_since(index).is_some());
+    }
+
+    #[test]
+    fn test_observe_before_restart() {
+        let mut supervisor = StreamSupervisor::new();
+
+        // nothing is dropped while the stream was not restarted
+        let observed = supervisor.observe(
+            0,
+            vec![
+                new_json("a", 10, "a1"),
+                new_json("b", 5, "b1"),
+                new_json("a", 10, "a1"),
+            ],
+        );
+
+        assert_eq!(get_messages(observed), vec!["a1", "b1", "a1"]);
+    }
+
+    #[test]
+    fn test_observe_after_restart() {
+        let mut supervisor = StreamSupervisor::new();
+
+        supervisor.observe(
+            0,
+            vec![
+                new_json("a", 10, "a1"),
+                new_json("a", 20, "a2"),
+                new_json("a", 20, "a3"),
+                new_json("b", 12, "b1"),
+            ],
+        );
+        restart(&mut supervisor, 0);
+
+        let observed = supervisor.observe(
+            0,
+            vec![
+                new_json("a", 10, "a1"),
+                new_json("a", 20, "a2"),
+                new_json("a", 20, "a4"),
+                new_json("b", 12, "b1"),
+                // older than the last entry of the stream, but not seen from this pod
+                new_json("b", 16, "b2"),
+                new_json("c", 1, "c1"),
+                new_json("a", 20, "a3"),
+                new_json("a", 21, "a5"),
+            ],
+        );
+
+        assert_eq!(get_messages(observed), vec!["a4", "b2", "c1", "a5"]);
+    }
+
+    #[test]
+    fn test_observe_after_restart_window() {
+        let mut supervisor = StreamSupervisor::new();
+
+        supervisor.observe(0, vec![new_json("a", 10, "a1")]);
+        restart(&mut supervisor, 0);
+
+        // once a newer entry was seen, the restart window of the pod is over
+        let observed = supervisor.observe(
+            0,
+            vec![
+                new_json("a", 10, "a1"),
+                new_json("a", 11, "a2"),
+                new_json("a", 5, "a3"),
+            ],
+        );
+
+        assert_eq!(get_messages(observed), vec!["a2", "a3"]);
+
+        // other streams are not affected
+        let observed = supervisor.observe(1, vec![new_json("a", 10, "a1")]);
+
+        assert_eq!(get_messages(observed), vec!["a1"]);
+    }
+
+    #[test]
+    fn test_schedule_restart() {
+        let mut supervisor = StreamSupervisor::new();
+
+        assert_eq!(supervisor.schedule_restart(0), Duration::from_secs(1));
+        assert_eq!(supervisor.schedule_restart(0), Duration::from_secs(2));
+        assert_eq!(supervisor.schedule_restart(0), Duration::from_secs(4));
+
+        for _ in 0..10 {
+            supervisor.schedule_restart(0);
+        }
+
+        assert_eq!(supervisor.schedule_restart(0), MAX_BACKOFF);
+
+        // the backoff has not passed yet
+        assert_eq!(supervisor.pop_restart_since(0), None);
+        assert_eq!(supervisor.pop_restart_since(1), None);
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut supervisor = StreamSupervisor::new();
+        let mut streamer = CommandStreamer::new("true", &vec![], None).unwrap();
+
+        streamer.get_all_lines(true, false).unwrap();
+
+        assert!(supervisor.check_exited(0, &mut streamer).is_some());
+
+        supervisor.get_stream(0).restart_at = Some(Instant::now());
+        supervisor.finish(0);
+
+        // a finished stream is neither restarted nor reported as exited again
+        assert_eq!(supervisor.pop_restart_since(0), None);
+        assert!(supervisor.check_exited(0, &mut streamer).is_none());
+
+        // new streams are looked for right away, then after the interval
+        assert!(supervisor.pop_spawn_new());
+        assert!(!supervisor.pop_spawn_new());
+    }
+}
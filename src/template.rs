@@ -0,0 +1,216 @@
+use anyhow::{Error, Result};
+use serde_json::Value;
+
+use crate::json_utils::{get_value_by_path, value_to_string};
+use crate::stern_json::SternJSON;
+
+pub const DEFAULT_TEMPLATE: &str = "{context} {pod} {container} {timestamp}    {message}";
+
+enum TemplatePart {
+    Literal(String),
+    Field(String),
+}
+
+/// An output line template like `{ts} [{level}] {pod}/{container}: {message}`.
+///
+/// Use `{{` and `}}` for literal braces.
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c == '{' && chars.peek() == Some(&'{') {
+                chars.next();
+                literal.push('{');
+            } else if c == '}' && chars.peek() == Some(&'}') {
+                chars.next();
+                literal.push('}');
+            } else if c == '{' {
+                let mut field = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(Error::msg(format!(
+                                "unclosed field \"{{{}\" in template \"{}\"",
+                                field, template
+                            )))
+                        }
+                    }
+                }
+
+                if field.trim().is_empty() {
+                    return Err(Error::msg(format!(
+                        "empty field in template \"{}\"",
+                        template
+                    )));
+                }
+
+                if !literal.is_empty() {
+                    parts.push(TemplatePart::Literal(literal.to_string()));
+                    literal.clear();
+                }
+
+                parts.push(TemplatePart::Field(field.trim().to_string()));
+            } else if c == '}' {
+                return Err(Error::msg(format!(
+                    "unexpected \"}}\" in template \"{}\", use \"}}}}\" for a literal one",
+                    template
+                )));
+            } else {
+                literal.push(c);
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+
+        return Ok(Template { parts });
+    }
+
+    /// Renders the template, fields not resolved by `resolve` are left empty.
+    pub fn render(&self, resolve: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut rendered = String::new();
+
+        for ipart in &self.parts {
+            match ipart {
+                TemplatePart::Literal(literal) => rendered.push_str(literal),
+                TemplatePart::Field(field) => {
+                    if let Some(value) = resolve(field) {
+                        rendered.push_str(&value);
+                    }
+                }
+            }
+        }
+
+        return rendered;
+    }
+}
+
+/// A log entry being printed, formats each of its output lines with a template.
+///
+/// Fields available to the template are `context`, `namespace`, `node`, `pod`, `container`,
+/// `timestamp` (or `ts`), `message` (as printed, after fix-up), `raw`, `request_id`, `level`,
+/// every `SternJSON` field by name (`pod_name`, `podName`...) and any field of
+/// the message parsed as JSON by its dotted path (use `fields.` prefix to skip the others).
+pub struct EntryLine<'a> {
+    pub template: &'a Template,
+    pub context: &'a str,
+    pub json: &'a SternJSON,
+}
+
+impl EntryLine<'_> {
+    pub fn format(&self, message: &str) -> String {
+        return self.render(self.template, Some(message));
+    }
+
+    /// Renders any template for this entry, without a printed message
+    /// `{message}` resolves to the field of the JSON message.
+    pub fn render(&self, template: &Template, message: Option<&str>) -> String {
+        return template.render(&|field| self.resolve_field(field, message));
+    }
+
+    fn resolve_field(&self, field: &str, message: Option<&str>) -> Option<String> {
+        let json = self.json;
+
+        return match field {
+            "context" => Some(self.context.to_string()),
+            "namespace" => Some(json.namespace.to_string()),
+            "node" | "node_name" | "nodeName" => Some(json.node_name.to_string()),
+            "pod" | "pod_name" | "podName" => Some(json.pod_name.to_string()),
+            "container" | "container_name" | "containerName" => {
+                Some(json.container_name.to_string())
+            }
+            "ts" | "timestamp" => Some(json.timestamp.to_string()),
+            "message" if message.is_some() => message.map(|message| message.to_string()),
+            "raw" => Some(json.raw.to_string()),
+            "level" if json.level.is_some() => json.level.map(|level| level.to_string()),
+            "is_valid" => Some(json.is_valid.to_string()),
+            _ => self.resolve_internal_field(field.strip_prefix("fields.").unwrap_or(field)),
+        };
+    }
+
+    fn resolve_internal_field(&self, path: &str) -> Option<String> {
+        let internal_json_message = self.json.internal_json_message.as_ref()?;
+
+        return match get_value_by_path(internal_json_message, path)? {
+            Value::Null => None,
+            value => Some(value_to_string(value)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stern_json_regex::SternJSONRegEx;
+
+    fn render_fields(template: &str) -> String {
+        return Template::parse(template)
+            .unwrap()
+            .render(&|field| match field {
+                "a" => Some("A".into()),
+                _ => None,
+            });
+    }
+
+    #[test]
+    fn test_parse_and_render() {
+        assert_eq!(render_fields("x {a} y"), "x A y");
+        assert_eq!(render_fields("{ a }{a}"), "AA");
+        assert_eq!(render_fields("[{unknown}]"), "[]");
+        assert_eq!(render_fields("{{a}} {{{a}}}"), "{a} {A}");
+        assert_eq!(render_fields(""), "");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Template::parse("{a").is_err());
+        assert!(Template::parse("{ }").is_err());
+        assert!(Template::parse("a}").is_err());
+    }
+
+    #[test]
+    fn test_entry_line() {
+        let template =
+            Template::parse("{ts} [{level}] {context} {pod}/{container}: {message}").unwrap();
+        let json = SternJSON::from_fields(
+            "raw line",
+            r#"2024-01-02T03:04:05.1Z {"message": "m", "level": "error", "user": {"id": 7}, "context": "inner"}"#,
+            "node",
+            "ns",
+            "pod",
+            "app",
+            Some(&SternJSONRegEx::new()),
+        );
+        let entry_line = EntryLine {
+            template: &template,
+            context: "c1",
+            json: &json,
+        };
+
+        assert_eq!(
+            entry_line.format("printed"),
+            "2024-01-02T03:04:05.1Z [error] c1 pod/app: printed"
+        );
+
+        // without a printed message the field of the JSON message is used
+        let template =
+            Template::parse("{message} {user.id} {fields.context} {raw} {podName} {missing}")
+                .unwrap();
+
+        assert_eq!(
+            entry_line.render(&template, None),
+            "m 7 inner raw line pod "
+        );
+    }
+}